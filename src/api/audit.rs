@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// 内存中保留的审计记录条数
+pub const DEFAULT_AUDIT_CAPACITY: usize = 1000;
+
+/// 一次成功的配置读取（只记录 key 名，不记录值）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AuditEntry {
+    /// Unix 时间戳（秒）
+    pub timestamp: u64,
+    /// API Key 标识（label 或脱敏后的 key）
+    pub key_id: String,
+    pub project: String,
+    pub environment: String,
+    /// 读取的配置项，整体读取时为 "all"
+    pub key: String,
+}
+
+impl AuditEntry {
+    pub fn new(key_id: &str, project: &str, environment: &str, key: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            timestamp,
+            key_id: key_id.to_string(),
+            project: project.to_string(),
+            environment: environment.to_string(),
+            key: key.to_string(),
+        }
+    }
+}
+
+/// 只追加的访问审计日志：内存环形缓冲 + 可选 JSON Lines 文件
+pub struct AuditLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<AuditEntry>>,
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(VecDeque::new()),
+            file: None,
+        }
+    }
+
+    /// 同时追加写入文件
+    pub fn with_file(mut self, path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.file = Some(Mutex::new(file));
+        Ok(self)
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        if let Some(file) = &self.file {
+            let line = serde_json::to_string(&entry).unwrap_or_default();
+            if let Err(e) = writeln!(file.lock().unwrap(), "{}", line) {
+                tracing::warn!("写入审计日志失败: {}", e);
+            }
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// 最近的记录，按时间从新到旧
+    pub fn recent(&self, limit: usize) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_AUDIT_CAPACITY)
+    }
+}
@@ -1,17 +1,70 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::SystemTime;
 
+use arc_swap::ArcSwap;
 use axum::extract::{Path, Query, State};
-use axum::http::{HeaderMap, StatusCode};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
 use axum::response::{IntoResponse, Json, Response};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
 
-use crate::core::ConfigCenter;
+use super::audit::{AuditEntry, AuditLog};
+use super::metrics::Metrics;
+use super::rate_limit::RateLimiter;
+use crate::core::{
+    flatten_config, ArrayFormat, BoolFormat, ConfigCenter, ExportFormat, ExportOptions, NullFormat,
+    ReloadStatus, ValidationIssue,
+};
 use crate::error::ConfigError;
 
-/// 共享状态类型
-pub type AppState = Arc<RwLock<ConfigCenter>>;
+/// 共享状态
+#[derive(Clone)]
+pub struct AppState {
+    /// 当前配置快照：读取无锁，重载时整体替换
+    pub center: Arc<ArcSwap<ConfigCenter>>,
+    pub metrics: Arc<Metrics>,
+    /// 配置读取审计日志
+    pub audit: Arc<AuditLog>,
+    /// 按 API Key 限流（未配置则不限流）
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl AppState {
+    pub fn new(center: ConfigCenter) -> Self {
+        Self {
+            center: Arc::new(ArcSwap::from_pointee(center)),
+            metrics: Arc::new(Metrics::new()),
+            audit: Arc::new(AuditLog::default()),
+            rate_limiter: None,
+        }
+    }
+
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Arc::new(audit);
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(limiter));
+        self
+    }
+
+    /// 按变更路径重载并替换当前配置；失败时保留原配置并记录失败状态
+    pub fn reload(&self, changed: &[std::path::PathBuf]) -> Result<(), ConfigError> {
+        let current = self.center.load();
+        match current.reloaded_paths(changed) {
+            Ok(center) => {
+                self.center.store(Arc::new(center));
+                self.metrics.reloads.inc();
+                Ok(())
+            }
+            Err(e) => {
+                current.record_reload_failure(&e);
+                Err(e)
+            }
+        }
+    }
+}
 
 // ---- 响应结构体 ----
 
@@ -20,6 +73,29 @@ pub struct AllConfigsResponse {
     pub project: String,
     pub environment: String,
     pub configs: HashMap<String, serde_json::Value>,
+    /// 通过 `?keys=` 请求但不存在的配置项
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing: Option<Vec<String>>,
+    /// `?debug=sources` 时参与合并的来源文件（相对配置目录，低优先级在前）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+pub struct ChecksumResponse {
+    pub project: String,
+    pub environment: String,
+    /// 合并配置的 SHA-256（十六进制）
+    pub checksum: String,
+}
+
+#[derive(Serialize)]
+pub struct ValidateResponse {
+    pub project: String,
+    pub environment: String,
+    /// 没有发现任何问题
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
 }
 
 #[derive(Serialize)]
@@ -28,15 +104,181 @@ pub struct SingleConfigResponse {
     pub value: serde_json::Value,
 }
 
+#[derive(Serialize)]
+pub struct EnvironmentsResponse {
+    pub project: String,
+    pub environments: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct EnvironmentRef {
+    pub project: String,
+    pub environment: String,
+}
+
+#[derive(Serialize)]
+pub struct SharedEnvironmentsResponse {
+    /// 全局共享配置（shared/ 根目录）的环境
+    pub environments: Vec<String>,
+    /// 命名共享组 -> 环境
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectSummary {
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub environments: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+}
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+    /// ok：加载无警告；degraded：有文件被跳过
+    pub status: &'static str,
+    pub projects: usize,
+    pub config_items: usize,
+    pub load_warnings: usize,
+    pub revision: u64,
+    pub last_reload: ReloadStatus,
+}
+
+#[derive(Serialize)]
+pub struct ReloadStatusResponse {
+    pub revision: u64,
+    pub last_reload: ReloadStatus,
+}
+
 #[derive(Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// 机器可读的错误码，如 `PROJECT_NOT_FOUND`
+    pub code: &'static str,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ReadParams {
+    /// 响应格式：json（默认）或 yaml
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ConfigsParams {
+    #[serde(default)]
+    pub format: Option<String>,
+    /// 只返回指定的配置项（逗号分隔）
+    #[serde(default)]
+    pub keys: Option<String>,
+    /// 嵌套对象/数组展开为点分 key
+    #[serde(default)]
+    pub flat: bool,
+    /// 调试信息：目前只支持 `sources`（附带参与合并的来源文件，仅管理员）
+    #[serde(default)]
+    pub debug: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ProjectsParams {
+    /// 只列出带有该标签的项目
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// 同时列出已归档的项目
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+/// 分页参数（负数由反序列化直接拒绝）
+#[derive(Deserialize, Default)]
+pub struct PageParams {
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+/// 未指定 `?limit=` 时的每页条数
+const DEFAULT_PAGE_LIMIT: usize = 100;
+/// `?limit=` 允许的最大值
+const MAX_PAGE_LIMIT: usize = 1000;
+
+/// 分页响应
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// 过滤后、分页前的总条数
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// 截取一页；offset 超出末尾时返回空列表和真实总数
+fn paginate<T>(
+    items: Vec<T>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Page<T>, ConfigError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if limit == 0 || limit > MAX_PAGE_LIMIT {
+        return Err(ConfigError::InvalidParameter(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_LIMIT
+        )));
+    }
+    let offset = offset.unwrap_or(0);
+    let total = items.len();
+    let items = items.into_iter().skip(offset).take(limit).collect();
+    Ok(Page {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+#[derive(Deserialize, Default)]
+pub struct AuditParams {
+    #[serde(default)]
+    pub limit: Option<usize>,
 }
 
 #[derive(Deserialize, Default)]
 pub struct ExportParams {
     #[serde(default)]
     pub prefix: Option<String>,
+    /// 只导出以此开头的配置 key（原始 key，区分大小写）
+    #[serde(default)]
+    pub key_prefix: Option<String>,
+    /// 逗号分隔的不导出 key 列表
+    #[serde(default)]
+    pub exclude: Option<String>,
+    /// 不导出以此开头的 key；排除优先于 `key_prefix`
+    #[serde(default)]
+    pub exclude_prefix: Option<String>,
+    /// 渲染 Tera 模板值
+    #[serde(default)]
+    pub render: bool,
+    /// 数组格式：json（默认）或 csv
+    #[serde(default)]
+    pub array_format: ArrayFormat,
+    /// csv 数组的分隔符
+    #[serde(default)]
+    pub separator: Option<String>,
+    /// 输出语法：shell（默认）、powershell、cmd 或 fish
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// 布尔值格式：literal（默认，true/false）或 numeric（1/0）
+    #[serde(default)]
+    pub bool_format: BoolFormat,
+    /// null 值：empty（默认，空字符串）、omit（省略该 key）或 literal（`null`）
+    #[serde(default, rename = "null")]
+    pub null_as: NullFormat,
 }
 
 // ---- ConfigError -> HTTP Response ----
@@ -47,82 +289,1978 @@ impl IntoResponse for ConfigError {
             ConfigError::ProjectNotFound(_) => StatusCode::NOT_FOUND,
             ConfigError::EnvironmentNotFound(_) => StatusCode::NOT_FOUND,
             ConfigError::ConfigItemNotFound(_) => StatusCode::NOT_FOUND,
+            ConfigError::SharedGroupNotFound(_) => StatusCode::NOT_FOUND,
             ConfigError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             ConfigError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ConfigError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ConfigError::InvalidParameter(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
-        (
+        let retry_after = match &self {
+            ConfigError::RateLimited(secs) => Some(secs.to_string()),
+            _ => None,
+        };
+        let mut response = (
             status,
             Json(ErrorResponse {
                 error: self.to_string(),
+                code: self.code(),
             }),
         )
-            .into_response()
+            .into_response();
+        if let Some(value) = retry_after.and_then(|v| v.parse().ok()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        response
+    }
+}
+
+// ---- 内容协商 ----
+
+/// `?format=yaml` 优先，否则看 Accept 头是否要求 YAML
+fn wants_yaml(headers: &HeaderMap, format: Option<&str>) -> bool {
+    match format {
+        Some(f) => f.eq_ignore_ascii_case("yaml") || f.eq_ignore_ascii_case("yml"),
+        None => headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(|accept| accept.contains("yaml"))
+            .unwrap_or(false),
+    }
+}
+
+/// 按协商结果序列化响应体
+fn negotiate<T: Serialize>(body: &T, yaml: bool) -> Result<Response, ConfigError> {
+    if !yaml {
+        return Ok(Json(body).into_response());
     }
+    let text = serde_yaml::to_string(body)
+        .map_err(|e| ConfigError::StorageError(format!("yaml serialization failed: {}", e)))?;
+    Ok(([(header::CONTENT_TYPE, "application/yaml")], text).into_response())
 }
 
 // ---- 内联认证 ----
 
-fn validate_request(
-    center: &ConfigCenter,
-    headers: &HeaderMap,
-    project: &str,
-) -> Result<(), ConfigError> {
-    let api_key = headers
-        .get("X-API-Key")
+/// 调用方身份
+enum Identity {
+    /// 全局管理员 Key，可访问任意项目
+    Admin,
+    /// 项目 Key，只能访问所属项目
+    Project(String),
+}
+
+/// 认证通过的调用方：请求时的配置快照 + 身份
+struct Caller {
+    center: Arc<ConfigCenter>,
+    identity: Identity,
+    /// API Key 标识（用于审计，不含完整 key）
+    key_id: String,
+}
+
+impl Identity {
+    fn can_access(&self, project: &str) -> bool {
+        match self {
+            Identity::Admin => true,
+            Identity::Project(p) => p == project,
+        }
+    }
+}
+
+impl Caller {
+    fn can_access(&self, project: &str) -> bool {
+        self.identity.can_access(project)
+    }
+}
+
+/// 单个请求最多可携带的 API Key 数，限制每个请求能尝试的 key
+const MAX_KEYS_PER_REQUEST: usize = 4;
+
+/// 读取 API Key：优先 `Authorization: Bearer <key>`，没有 Bearer 凭证时使用 X-API-Key。
+/// 两者同时存在时只看 Bearer，不会因为 X-API-Key 无效而拒绝。
+/// 网关可能把多个 X-API-Key 合并为 `a, b`，因此按逗号拆分（Bearer 只取一个）
+fn api_keys_from_headers(headers: &HeaderMap) -> Result<Vec<&str>, ConfigError> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| ConfigError::Unauthorized("missing X-API-Key header".to_string()))?;
+        .and_then(|v| v.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim());
+    if let Some(token) = bearer {
+        return Ok(vec![token]);
+    }
+    let keys: Vec<&str> = headers
+        .get_all("X-API-Key")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .collect();
+    if keys.is_empty() {
+        return Err(ConfigError::Unauthorized(
+            "missing Authorization: Bearer or X-API-Key header".to_string(),
+        ));
+    }
+    if keys.len() > MAX_KEYS_PER_REQUEST {
+        return Err(ConfigError::Unauthorized(format!(
+            "too many api keys in request (max {})",
+            MAX_KEYS_PER_REQUEST
+        )));
+    }
+    Ok(keys)
+}
+
+/// 取当前配置快照并校验 API Key（失败计入指标），再按 key 限流
+fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<Caller, ConfigError> {
+    authenticate_preferring(state, headers, |_| true)
+}
+
+/// 同 [`authenticate`]；携带多个有效 key 时优先选用满足 `prefer` 的那个，都不满足时取第一个
+fn authenticate_preferring(
+    state: &AppState,
+    headers: &HeaderMap,
+    prefer: impl Fn(&Identity) -> bool,
+) -> Result<Caller, ConfigError> {
+    let center = state.center.load_full();
+    let resolve = |key| {
+        if center.is_admin_key(key) {
+            return Some((key, Identity::Admin, "admin".to_string()));
+        }
+        let (project, entry) = center.find_api_key(key).ok()?;
+        Some((key, Identity::Project(project.to_string()), entry.id()))
+    };
+    let (api_key, identity, key_id) = api_keys_from_headers(headers)
+        .and_then(|keys| {
+            let mut valid: Vec<_> = keys.into_iter().filter_map(resolve).collect();
+            if valid.is_empty() {
+                return Err(ConfigError::Unauthorized("invalid api key".to_string()));
+            }
+            let index = valid
+                .iter()
+                .position(|(_, identity, _)| prefer(identity))
+                .unwrap_or(0);
+            Ok(valid.swap_remove(index))
+        })
+        .inspect_err(|_| state.metrics.auth_failures.inc())?;
+    if let Some(limiter) = &state.rate_limiter {
+        limiter.check(api_key)?;
+    }
+    Ok(Caller {
+        center,
+        identity,
+        key_id,
+    })
+}
 
-    let (key_project, _) = center.validate_api_key(api_key)?;
+/// 仅允许管理员 Key
+fn authenticate_admin(state: &AppState, headers: &HeaderMap) -> Result<Caller, ConfigError> {
+    let caller = authenticate_preferring(state, headers, |identity| {
+        matches!(identity, Identity::Admin)
+    })?;
+    if !matches!(caller.identity, Identity::Admin) {
+        state.metrics.auth_failures.inc();
+        return Err(ConfigError::Forbidden("admin key required".to_string()));
+    }
+    Ok(caller)
+}
 
-    if key_project != project {
+/// 校验 API Key 是否有权访问指定项目；已归档项目视为不存在
+fn authorize(state: &AppState, headers: &HeaderMap, project: &str) -> Result<Caller, ConfigError> {
+    let caller = authenticate_preferring(state, headers, |identity| identity.can_access(project))?;
+    if !caller.can_access(project) {
+        state.metrics.auth_failures.inc();
         return Err(ConfigError::Forbidden(format!(
             "api key not authorized for project: {}",
             project
         )));
     }
+    if caller.center.is_archived(project) {
+        return Err(ConfigError::ProjectNotFound(project.to_string()));
+    }
+    Ok(caller)
+}
 
-    Ok(())
+/// 记录一次成功的配置读取
+fn record_read(state: &AppState, caller: &Caller, project: &str, env: &str, key: &str) {
+    state
+        .metrics
+        .config_reads
+        .with_label_values(&[project, env])
+        .inc();
+    state
+        .audit
+        .record(AuditEntry::new(&caller.key_id, project, env, key));
 }
 
 // ---- 处理器 ----
 
 /// GET /api/v1/projects/{project}/envs/{env}/configs
+///
+/// 响应头带 `Last-Modified` 和 `X-Config-Revision`；完整配置（未使用 `keys`/`flat`/`debug`）
+/// 另带弱 `ETag`（合并配置的校验和，JSON/YAML、压缩与否共用）。
+/// HEAD 请求由路由自动处理，返回相同的状态码和响应头，不含响应体，也不计为一次读取
 pub async fn get_all_configs(
-    State(center): State<AppState>,
+    State(state): State<AppState>,
+    method: Method,
+    headers: HeaderMap,
+    Path((project, env)): Path<(String, String)>,
+    Query(params): Query<ConfigsParams>,
+) -> Result<Response, ConfigError> {
+    let caller = authorize(&state, &headers, &project)?;
+    let sources = match params.debug.as_deref() {
+        None => None,
+        Some("sources") => {
+            if !matches!(caller.identity, Identity::Admin) {
+                state.metrics.auth_failures.inc();
+                return Err(ConfigError::Forbidden(
+                    "admin key required for debug=sources".to_string(),
+                ));
+            }
+            Some(caller.center.source_paths(&project, &env)?)
+        }
+        Some(other) => {
+            return Err(ConfigError::InvalidParameter(format!(
+                "unknown debug option: {}",
+                other
+            )))
+        }
+    };
+    let mut configs = caller.center.get_merged_config(&project, &env)?;
+    let mut missing = None;
+    match params.keys.as_deref() {
+        Some(keys) => {
+            let requested: Vec<&str> = keys
+                .split(',')
+                .map(|k| k.trim())
+                .filter(|k| !k.is_empty())
+                .collect();
+            configs.retain(|k, _| requested.contains(&k.as_str()));
+            missing = Some(
+                requested
+                    .iter()
+                    .filter(|k| !configs.contains_key(**k))
+                    .map(|k| k.to_string())
+                    .collect(),
+            );
+            if method != Method::HEAD {
+                record_read(&state, &caller, &project, &env, &requested.join(","));
+            }
+        }
+        None if method != Method::HEAD => record_read(&state, &caller, &project, &env, "all"),
+        None => {}
+    }
+    // ETag 只描述完整配置，子集、展开或调试输出是不同的表示
+    let full = missing.is_none() && !params.flat && sources.is_none();
+    if params.flat {
+        configs = flatten_config(&configs);
+    }
+    let checksum = caller.center.config_checksum(&project, &env)?;
+    let last_modified = caller.center.last_modified(&project, &env)?;
+    let mut response = negotiate(
+        &AllConfigsResponse {
+            project,
+            environment: env,
+            configs,
+            missing,
+            sources,
+        },
+        wants_yaml(&headers, params.format.as_deref()),
+    )?;
+    let response_headers = response.headers_mut();
+    if full {
+        if let Ok(value) = HeaderValue::from_str(&format!("W/\"{}\"", checksum)) {
+            response_headers.insert(header::ETAG, value);
+            response_headers.insert(
+                header::VARY,
+                HeaderValue::from_static("Accept, Accept-Encoding"),
+            );
+        }
+    }
+    response_headers.insert(
+        "X-Config-Revision",
+        HeaderValue::from(caller.center.revision()),
+    );
+    set_last_modified(&mut response, last_modified);
+    Ok(response)
+}
+
+/// 设置 `Last-Modified`：参与合并的来源文件中最新的修改时间（纯内存存储没有该时间）
+fn set_last_modified(response: &mut Response, modified: Option<SystemTime>) {
+    if let Some(modified) = modified {
+        if let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(modified)) {
+            response.headers_mut().insert(header::LAST_MODIFIED, value);
+        }
+    }
+}
+
+/// GET /api/v1/projects/{project}/envs/{env}/checksum
+///
+/// 只返回合并配置的校验和，客户端无需下载完整配置即可判断是否变化
+pub async fn get_checksum(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((project, env)): Path<(String, String)>,
+) -> Result<Json<ChecksumResponse>, ConfigError> {
+    let caller = authorize(&state, &headers, &project)?;
+    let checksum = caller.center.config_checksum(&project, &env)?;
+    Ok(Json(ChecksumResponse {
+        project,
+        environment: env,
+        checksum,
+    }))
+}
+
+/// GET /api/v1/projects/{project}/envs/{env}/validate（仅管理员）
+///
+/// 汇总该环境的全部问题，供 CI 作为单一检查项
+pub async fn validate_env(
+    State(state): State<AppState>,
     headers: HeaderMap,
     Path((project, env)): Path<(String, String)>,
-) -> Result<Json<AllConfigsResponse>, ConfigError> {
-    let center = center.read().await;
-    validate_request(&center, &headers, &project)?;
-    let configs = center.get_merged_config(&project, &env)?;
-    Ok(Json(AllConfigsResponse {
+) -> Result<Json<ValidateResponse>, ConfigError> {
+    let caller = authenticate_admin(&state, &headers)?;
+    let issues = caller.center.validate_env(&project, &env)?;
+    Ok(Json(ValidateResponse {
         project,
         environment: env,
-        configs,
+        valid: issues.is_empty(),
+        issues,
     }))
 }
 
 /// GET /api/v1/projects/{project}/envs/{env}/configs/{key}
 pub async fn get_single_config(
-    State(center): State<AppState>,
+    State(state): State<AppState>,
+    method: Method,
     headers: HeaderMap,
     Path((project, env, key)): Path<(String, String, String)>,
-) -> Result<Json<SingleConfigResponse>, ConfigError> {
-    let center = center.read().await;
-    validate_request(&center, &headers, &project)?;
-    let value = center.get_merged_config_item(&project, &env, &key)?;
-    Ok(Json(SingleConfigResponse { key, value }))
+    Query(params): Query<ReadParams>,
+) -> Result<Response, ConfigError> {
+    let caller = authorize(&state, &headers, &project)?;
+    let value = caller.center.get_merged_config_item(&project, &env, &key)?;
+    if method != Method::HEAD {
+        record_read(&state, &caller, &project, &env, &key);
+    }
+    let mut response = negotiate(
+        &SingleConfigResponse { key, value },
+        wants_yaml(&headers, params.format.as_deref()),
+    )?;
+    set_last_modified(&mut response, caller.center.last_modified(&project, &env)?);
+    Ok(response)
 }
 
 /// GET /api/v1/projects/{project}/envs/{env}/export
 pub async fn export_env(
-    State(center): State<AppState>,
+    State(state): State<AppState>,
+    method: Method,
     headers: HeaderMap,
     Path((project, env)): Path<(String, String)>,
     Query(params): Query<ExportParams>,
 ) -> Result<String, ConfigError> {
-    let center = center.read().await;
-    validate_request(&center, &headers, &project)?;
-    center.get_env_export(&project, &env, params.prefix.as_deref())
+    let caller = authorize(&state, &headers, &project)?;
+    let options = ExportOptions {
+        prefix: params.prefix,
+        key_prefix: params.key_prefix,
+        exclude: params
+            .exclude
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|k| k.trim())
+            .filter(|k| !k.is_empty())
+            .map(|k| k.to_string())
+            .collect(),
+        exclude_prefix: params.exclude_prefix,
+        render: params.render,
+        array_format: params.array_format,
+        separator: params.separator,
+        format: params.format,
+        bool_format: params.bool_format,
+        null_as: params.null_as,
+    };
+    let export = caller.center.get_env_export(&project, &env, &options)?;
+    if method != Method::HEAD {
+        record_read(&state, &caller, &project, &env, "all");
+    }
+    Ok(export)
+}
+
+/// GET /api/v1/projects
+///
+/// 管理员 Key 列出全部项目，项目 Key 仅列出所属项目
+pub async fn list_projects(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ProjectsParams>,
+) -> Result<Json<Page<ProjectSummary>>, ConfigError> {
+    let caller = authenticate(&state, &headers)?;
+    let mut names: Vec<&str> = match &caller.identity {
+        Identity::Admin => caller.center.list_projects(),
+        Identity::Project(p) => vec![p.as_str()],
+    };
+    if !params.archived {
+        names.retain(|name| !caller.center.is_archived(name));
+    }
+    if let Some(tag) = &params.tag {
+        names.retain(|name| {
+            caller
+                .center
+                .project_meta(name)
+                .is_ok_and(|meta| meta.tags.contains(tag))
+        });
+    }
+    let page = paginate(names, params.limit, params.offset)?;
+    let items = page
+        .items
+        .into_iter()
+        .map(|name| project_summary(&caller.center, name))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Json(Page {
+        items,
+        total: page.total,
+        limit: page.limit,
+        offset: page.offset,
+    }))
+}
+
+/// GET /api/v1/projects/{project}/envs
+///
+/// `?format=ndjson` 时改为逐行输出每个环境的合并配置
+pub async fn list_environments(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(project): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Response, ConfigError> {
+    let caller = authorize(&state, &headers, &project)?;
+    if params
+        .format
+        .as_deref()
+        .is_some_and(|f| f.eq_ignore_ascii_case("ndjson"))
+    {
+        let body = caller.center.export_project_ndjson(&project)?;
+        record_read(&state, &caller, &project, "*", "all");
+        return Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response());
+    }
+    let environments = caller
+        .center
+        .list_environments(&project)?
+        .into_iter()
+        .map(String::from)
+        .collect();
+    Ok(Json(EnvironmentsResponse {
+        project,
+        environments,
+    })
+    .into_response())
+}
+
+fn project_summary(center: &ConfigCenter, project: &str) -> Result<ProjectSummary, ConfigError> {
+    let meta = center.project_meta(project)?;
+    Ok(ProjectSummary {
+        name: project.to_string(),
+        description: meta.description.clone(),
+        tags: meta.tags.clone(),
+        archived: meta.archived,
+        environments: center
+            .list_environments(project)?
+            .into_iter()
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// GET /api/v1/environments（仅管理员）
+pub async fn list_all_environments(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<EnvironmentRef>>, ConfigError> {
+    let caller = authenticate_admin(&state, &headers)?;
+    let all = caller
+        .center
+        .list_all_environments()
+        .into_iter()
+        .filter(|(project, _)| !caller.center.is_archived(project))
+        .map(|(project, environment)| EnvironmentRef {
+            project,
+            environment,
+        })
+        .collect();
+    Ok(Json(paginate(all, params.limit, params.offset)?))
+}
+
+/// GET /api/v1/shared（仅管理员）
+pub async fn list_shared_environments(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SharedEnvironmentsResponse>, ConfigError> {
+    let caller = authenticate_admin(&state, &headers)?;
+    let center = &caller.center;
+    let groups = center
+        .list_shared_groups()
+        .into_iter()
+        .map(|group| {
+            let envs = center.list_shared_group_environments(group)?;
+            Ok((
+                group.to_string(),
+                envs.into_iter().map(String::from).collect(),
+            ))
+        })
+        .collect::<Result<_, ConfigError>>()?;
+    Ok(Json(SharedEnvironmentsResponse {
+        environments: center
+            .list_shared_environments()
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        groups,
+    }))
+}
+
+/// GET /api/v1/audit（仅管理员）
+pub async fn list_audit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<AuditParams>,
+) -> Result<Json<Vec<AuditEntry>>, ConfigError> {
+    authenticate_admin(&state, &headers)?;
+    let limit = params.limit.unwrap_or(100);
+    Ok(Json(state.audit.recent(limit)))
+}
+
+/// GET /health（无需认证）
+///
+/// 可作为就绪探针：最近一次重载失败时返回 503；跳过了文件只标记为 degraded，仍返回 200
+pub async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let center = state.center.load();
+    let load_warnings = center.load_warnings().len();
+    let last_reload = center.last_reload();
+    let code = if !last_reload.success {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    let body = Json(HealthResponse {
+        status: if load_warnings == 0 && last_reload.success {
+            "ok"
+        } else {
+            "degraded"
+        },
+        projects: center.list_projects().len(),
+        config_items: center.config_item_count(),
+        load_warnings,
+        revision: center.revision(),
+        last_reload,
+    });
+    (code, body)
+}
+
+/// GET /api/v1/reload-status（仅管理员）
+pub async fn reload_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ReloadStatusResponse>, ConfigError> {
+    let caller = authenticate_admin(&state, &headers)?;
+    Ok(Json(ReloadStatusResponse {
+        revision: caller.center.revision(),
+        last_reload: caller.center.last_reload(),
+    }))
+}
+
+/// GET /metrics（无需认证）
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let body = state.metrics.render(&state.center.load());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    /// 辅助：发送请求，返回 (状态码, 响应头, 响应体)
+    async fn send(state: &AppState, request: Request<Body>) -> (StatusCode, HeaderMap, String) {
+        let response = crate::api::create_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, headers, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    fn get(uri: &str, api_key: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(key) = api_key {
+            builder = builder.header("X-API-Key", key);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn get_with_headers(uri: &str, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn write_config(base: &std::path::Path, version: u32) {
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            format!("a: {}\nb: {}\n", version, version),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_reads_during_swap() {
+        let tmp_a = TempDir::new().unwrap();
+        let tmp_b = TempDir::new().unwrap();
+        write_config(tmp_a.path(), 1);
+        write_config(tmp_b.path(), 2);
+        let center_a = Arc::new(ConfigCenter::new(tmp_a.path()).unwrap());
+        let center_b = Arc::new(ConfigCenter::new(tmp_b.path()).unwrap());
+
+        let state = AppState::new(ConfigCenter::new(tmp_a.path()).unwrap());
+        state.center.store(center_a.clone());
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let state = state.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..500 {
+                        let center = state.center.load_full();
+                        let merged = center.get_merged_config("app", "default").unwrap();
+                        // 每次读取都来自同一份快照，a/b 不会跨版本混合
+                        assert_eq!(merged["a"], merged["b"]);
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..200 {
+            let next = if i % 2 == 0 { &center_b } else { &center_a };
+            state.center.store(next.clone());
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_readiness() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\nshared_groups: [org]\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(base.join("shared/org")).unwrap();
+        std::fs::write(base.join("shared/org/default.yaml"), "y: 1\n").unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        let (status, _, body) = send(&state, get("/health", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let mut json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let last_reload = json.as_object_mut().unwrap().remove("last_reload").unwrap();
+        assert_eq!(last_reload["success"], true);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "status": "ok",
+                "projects": 1,
+                "config_items": 3,
+                "load_warnings": 0,
+                "revision": 0
+            })
+        );
+
+        std::fs::write(base.join("projects/app/broken.yaml"), "a: [unclosed\n").unwrap();
+        state
+            .center
+            .store(Arc::new(state.center.load().reloaded().unwrap()));
+        let (status, _, body) = send(&state, get("/health", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["status"], "degraded");
+        assert_eq!(json["load_warnings"], 1);
+        assert_eq!(json["revision"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_config_reads_per_project_and_env() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(base.join("projects/app/prod.yaml"), "a: 2\n").unwrap();
+        std::fs::create_dir_all(base.join("projects/billing")).unwrap();
+        std::fs::write(
+            base.join("projects/billing/project.yaml"),
+            "api_keys:\n  - key: billing-key\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/billing/default.yaml"), "x: 1\n").unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        for (uri, key) in [
+            ("/api/v1/projects/app/envs/default/configs", "k"),
+            ("/api/v1/projects/app/envs/prod/configs/a", "k"),
+            ("/api/v1/projects/app/envs/prod/configs", "k"),
+            (
+                "/api/v1/projects/billing/envs/default/configs",
+                "billing-key",
+            ),
+            // 不存在的环境不计数
+            ("/api/v1/projects/app/envs/nope/configs", "k"),
+        ] {
+            send(&state, get(uri, Some(key))).await;
+        }
+
+        let (_, _, body) = send(&state, get("/metrics", None)).await;
+        let reads = |project: &str, env: &str| {
+            format!(
+                "configai_config_reads_total{{env=\"{}\",project=\"{}\"}}",
+                env, project
+            )
+        };
+        assert!(body.contains(&format!("{} 1", reads("app", "default"))));
+        assert!(body.contains(&format!("{} 2", reads("app", "prod"))));
+        assert!(body.contains(&format!("{} 1", reads("billing", "default"))));
+        assert!(!body.contains("env=\"nope\""));
+    }
+
+    #[tokio::test]
+    async fn test_debug_sources() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(base.join("projects/app/prod.yaml"), "a: 2\n").unwrap();
+        std::fs::create_dir_all(base.join("shared")).unwrap();
+        std::fs::write(base.join("shared/prod.yaml"), "s: 1\n").unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+        let uri = "/api/v1/projects/app/envs/prod/configs?debug=sources";
+
+        let (status, _, body) = send(&state, get(uri, Some("admin"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json["sources"],
+            serde_json::json!([
+                "shared/prod.yaml",
+                "projects/app/default.yaml",
+                "projects/app/prod.yaml"
+            ])
+        );
+        assert_eq!(json["configs"]["a"], 2);
+
+        // 项目 Key 无权查看来源；不带 debug 时不输出该字段
+        let (status, _, _) = send(&state, get(uri, Some("k"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (_, _, body) = send(
+            &state,
+            get("/api/v1/projects/app/envs/prod/configs", Some("admin")),
+        )
+        .await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(json.get("sources").is_none());
+
+        let (status, _, _) = send(
+            &state,
+            get(
+                "/api/v1/projects/app/envs/prod/configs?debug=timing",
+                Some("admin"),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_bearer_authentication() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let uri = "/api/v1/projects/app/envs/default/configs";
+        let status = |headers: &'static [(&'static str, &'static str)]| {
+            let state = state.clone();
+            async move { send(&state, get_with_headers(uri, headers)).await.0 }
+        };
+
+        assert_eq!(
+            status(&[("Authorization", "Bearer k")]).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&[("Authorization", "bearer k")]).await,
+            StatusCode::OK
+        );
+        assert_eq!(status(&[("X-API-Key", "k")]).await, StatusCode::OK);
+        assert_eq!(status(&[]).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(&[("Authorization", "Bearer wrong")]).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // 两者同时存在时以 Bearer 为准
+        assert_eq!(
+            status(&[("Authorization", "Bearer k"), ("X-API-Key", "wrong")]).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&[("Authorization", "Bearer wrong"), ("X-API-Key", "k")]).await,
+            StatusCode::UNAUTHORIZED
+        );
+        // 非 Bearer 方案（如代理使用的 Basic）不影响 X-API-Key
+        assert_eq!(
+            status(&[("Authorization", "Basic dXNlcjpwYXNz"), ("X-API-Key", "k")]).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_authorization_per_route() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("projects/billing")).unwrap();
+        std::fs::write(
+            base.join("projects/billing/project.yaml"),
+            "api_keys:\n  - key: billing-key\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/billing/default.yaml"), "a: 1\n").unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        // 项目取自路由参数：每种路由都只允许所属项目的 Key
+        for route in ["configs", "configs/a", "export", "checksum"] {
+            for (project, key, expected) in [
+                ("app", "k", StatusCode::OK),
+                ("billing", "billing-key", StatusCode::OK),
+                ("billing", "k", StatusCode::FORBIDDEN),
+                ("app", "billing-key", StatusCode::FORBIDDEN),
+            ] {
+                let uri = format!("/api/v1/projects/{}/envs/default/{}", project, route);
+                let (status, _, _) = send(&state, get(&uri, Some(key))).await;
+                assert_eq!(status, expected, "{} with {}", uri, key);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiple_api_keys() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("projects/billing")).unwrap();
+        std::fs::write(
+            base.join("projects/billing/project.yaml"),
+            "api_keys:\n  - key: billing-key\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/billing/default.yaml"), "a: 1\n").unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+        let uri = "/api/v1/projects/app/envs/default/configs";
+        let status = |key: &'static str| {
+            let state = state.clone();
+            async move { send(&state, get(uri, Some(key))).await.0 }
+        };
+
+        assert_eq!(status("k").await, StatusCode::OK);
+        assert_eq!(status("wrong, k").await, StatusCode::OK);
+        // 另一个项目的有效 key 排在前面时，仍选用有权访问该项目的 key
+        assert_eq!(status("billing-key,k").await, StatusCode::OK);
+        assert_eq!(status("billing-key").await, StatusCode::FORBIDDEN);
+        assert_eq!(status("wrong, other").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(" , ").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("a, b, c, d, k").await, StatusCode::UNAUTHORIZED);
+
+        // 多个 X-API-Key 请求头同样合并处理
+        let (status, _, _) = send(
+            &state,
+            get_with_headers(uri, &[("X-API-Key", "wrong"), ("X-API-Key", "k")]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_reload_status() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        let strict = crate::storage::LoadOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let center = ConfigCenter::with_options(base, &strict)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+        let status = |state: &AppState| {
+            let state = state.clone();
+            async move {
+                let (code, _, body) =
+                    send(&state, get("/api/v1/reload-status", Some("admin"))).await;
+                assert_eq!(code, StatusCode::OK);
+                serde_json::from_str::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        write_config(base, 2);
+        state.reload(&[]).unwrap();
+        let json = status(&state).await;
+        assert_eq!(json["revision"], 1);
+        assert_eq!(json["last_reload"]["success"], true);
+        assert!(json["last_reload"]["timestamp"].as_u64().unwrap() > 0);
+        assert!(json["last_reload"].get("error").is_none());
+
+        // 严格模式下文件损坏：重载失败，继续使用原配置
+        std::fs::write(base.join("projects/app/default.yaml"), "a: [unclosed\n").unwrap();
+        assert!(state.reload(&[]).is_err());
+        let json = status(&state).await;
+        assert_eq!(json["revision"], 1);
+        assert_eq!(json["last_reload"]["success"], false);
+        assert!(json["last_reload"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("default.yaml"));
+        let (code, _, body) = send(&state, get("/health", None)).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        let health: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["last_reload"]["success"], false);
+
+        // 修复后重载成功，探针恢复
+        write_config(base, 3);
+        state.reload(&[]).unwrap();
+        let (code, _, _) = send(&state, get("/health", None)).await;
+        assert_eq!(code, StatusCode::OK);
+
+        let (code, _, _) = send(&state, get("/api/v1/reload-status", Some("k"))).await;
+        assert_eq!(code, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+
+        let (status, _, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs", Some("k")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs", Some("bad")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // /metrics 无需认证
+        let (status, headers, body) = send(&state, get("/metrics", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        assert!(body.contains("configai_http_requests_total 3"));
+        assert!(body.contains("configai_auth_failures_total 1"));
+        assert!(body.contains("configai_config_reads_total{env=\"default\",project=\"app\"} 1"));
+        assert!(body.contains("configai_reloads_total 0"));
+        assert!(body.contains("configai_projects 1"));
+        assert!(body.contains("configai_environments 1"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_header() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+
+        let (_, first, _) = send(&state, get("/health", None)).await;
+        let (_, second, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs", None),
+        )
+        .await;
+
+        let first_id = first["X-Request-Id"].to_str().unwrap();
+        let second_id = second["X-Request-Id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(first_id).is_ok());
+        assert!(uuid::Uuid::parse_str(second_id).is_ok());
+        assert_ne!(first_id, second_id);
+    }
+
+    #[tokio::test]
+    async fn test_version_header() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+
+        for (uri, key) in [
+            ("/health", None),
+            ("/api/v1/projects/app/envs/default/configs/a", Some("k")),
+            ("/api/v1/projects/app/envs/default/configs/a", None),
+        ] {
+            let (_, headers, _) = send(&state, get(uri, key)).await;
+            assert_eq!(headers["X-Configai-Version"], env!("CARGO_PKG_VERSION"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let options = crate::api::RouterOptions {
+            cors_origins: vec!["https://dashboard.example.com".to_string()],
+            ..Default::default()
+        };
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/v1/projects/app/envs/default/configs")
+                .header("Origin", origin)
+                .header("Access-Control-Request-Method", "GET")
+                .header("Access-Control-Request-Headers", "x-api-key")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let router = crate::api::create_router_with(state.clone(), &options);
+        let response = router
+            .oneshot(preflight("https://dashboard.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers["Access-Control-Allow-Origin"],
+            "https://dashboard.example.com"
+        );
+        assert!(headers["Access-Control-Allow-Headers"]
+            .to_str()
+            .unwrap()
+            .contains("x-api-key"));
+        assert!(headers["Access-Control-Allow-Methods"]
+            .to_str()
+            .unwrap()
+            .contains("GET"));
+
+        // 未列出的来源不返回允许头
+        let router = crate::api::create_router_with(state.clone(), &options);
+        let response = router
+            .oneshot(preflight("https://evil.example.com"))
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get("Access-Control-Allow-Origin")
+            .is_none());
+
+        // 默认不启用 CORS
+        let (_, headers, _) = send(&state, preflight("https://dashboard.example.com")).await;
+        assert!(headers.get("Access-Control-Allow-Origin").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gzip_compression() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        let many_keys: String = (0..200)
+            .map(|i| format!("key_{}: value-{}\n", i, i))
+            .collect();
+        std::fs::write(base.join("projects/app/default.yaml"), many_keys).unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        let request = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("X-API-Key", "k")
+                .header("Accept-Encoding", "gzip")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (status, headers, _) =
+            send(&state, request("/api/v1/projects/app/envs/default/configs")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip");
+
+        // 小响应不压缩
+        let (status, headers, body) = send(&state, request("/health")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert!(serde_json::from_str::<serde_json::Value>(&body).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_key() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let burst = 3;
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap())
+            .with_rate_limiter(RateLimiter::new(0.001, burst as f64));
+        let uri = "/api/v1/projects/app/envs/default/configs";
+
+        for _ in 0..burst {
+            let (status, _, _) = send(&state, get(uri, Some("k"))).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, headers, body) = send(&state, get(uri, Some("k"))).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = headers[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after >= 1);
+        assert!(body.contains("rate limited"));
+
+        // 无效 key 不消耗令牌，仍返回 401
+        let (status, _, _) = send(&state, get(uri, Some("bad"))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_content_negotiation() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let uri = "/api/v1/projects/app/envs/default/configs";
+
+        // 默认 JSON
+        let (status, headers, body) = send(&state, get(uri, Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["configs"]["a"], 1);
+
+        // Accept: application/yaml
+        let request = Request::builder()
+            .uri(uri)
+            .header("X-API-Key", "k")
+            .header(header::ACCEPT, "application/yaml")
+            .body(Body::empty())
+            .unwrap();
+        let (status, headers, body) = send(&state, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/yaml");
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&body).unwrap();
+        assert_eq!(yaml["project"], "app");
+        assert_eq!(yaml["configs"]["b"], 1);
+
+        // ?format=yaml（单个配置项）
+        let (status, headers, body) =
+            send(&state, get(&format!("{}/a?format=yaml", uri), Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/yaml");
+        assert_eq!(body, "key: a\nvalue: 1\n");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_read_selected_keys() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(base.join("projects/app/default.yaml"), "a: 1\nb: 2\nc: 3\n").unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+        let uri = "/api/v1/projects/app/envs/default/configs";
+
+        let (status, _, body) = send(&state, get(&format!("{}?keys=a,c", uri), Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["configs"], serde_json::json!({"a": 1, "c": 3}));
+        assert_eq!(json["missing"], serde_json::json!([]));
+
+        let (_, _, body) = send(&state, get(&format!("{}?keys=b,nope", uri), Some("k"))).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["configs"], serde_json::json!({"b": 2}));
+        assert_eq!(json["missing"], serde_json::json!(["nope"]));
+
+        // 不带 keys 时返回全部，且不含 missing 字段
+        let (_, _, body) = send(&state, get(uri, Some("k"))).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["configs"], serde_json::json!({"a": 1, "b": 2, "c": 3}));
+        assert!(json.get("missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_last_modified_header() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("shared")).unwrap();
+        std::fs::write(base.join("shared/default.yaml"), "s: 1\n").unwrap();
+        let set_mtime = |path: &str, secs: u64| {
+            std::fs::File::options()
+                .write(true)
+                .open(base.join(path))
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+                .unwrap();
+        };
+        set_mtime("projects/app/default.yaml", 1_700_000_000);
+        set_mtime("shared/default.yaml", 1_600_000_000);
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+        let last_modified = |headers: &HeaderMap| {
+            httpdate::parse_http_date(headers[header::LAST_MODIFIED].to_str().unwrap()).unwrap()
+        };
+
+        // 取参与合并的文件中最新的修改时间
+        let (status, headers, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs", Some("k")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let first = last_modified(&headers);
+        assert_eq!(
+            first,
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)
+        );
+        let (_, headers, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs/a", Some("k")),
+        )
+        .await;
+        assert_eq!(last_modified(&headers), first);
+
+        // 修改共享文件并重载后随之前进
+        set_mtime("shared/default.yaml", 1_800_000_000);
+        state.reload(&[]).unwrap();
+        let (_, headers, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs", Some("k")),
+        )
+        .await;
+        assert!(last_modified(&headers) > first);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_endpoint() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let uri = "/api/v1/projects/app/envs/default/checksum";
+
+        let (status, _, body) = send(&state, get(uri, Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let checksum = json["checksum"].as_str().unwrap().to_string();
+        assert_eq!(checksum.len(), 64);
+        let (_, _, again) = send(&state, get(uri, Some("k"))).await;
+        assert_eq!(body, again);
+
+        write_config(tmp.path(), 2);
+        state
+            .center
+            .store(Arc::new(ConfigCenter::new(tmp.path()).unwrap()));
+        let (_, _, body) = send(&state, get(uri, Some("k"))).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_ne!(json["checksum"], checksum);
+
+        let (status, _, _) = send(&state, get(uri, None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_nested_env_routes() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        std::fs::create_dir_all(tmp.path().join("projects/app/regions")).unwrap();
+        std::fs::write(
+            tmp.path().join("projects/app/regions/us-east.yaml"),
+            "a: 9\n",
+        )
+        .unwrap();
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let base = "/api/v1/projects/app/envs/regions%2Fus-east";
+
+        let (status, _, body) = send(&state, get(&format!("{}/configs", base), Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["environment"], "regions/us-east");
+        assert_eq!(json["configs"]["a"], 9);
+
+        let (status, _, body) = send(&state, get(&format!("{}/configs/a", base), Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("9"));
+        let (status, _, body) = send(&state, get(&format!("{}/export", base), Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("export A=9"));
+
+        // 未编码的 `/` 被当作路径分隔符，匹配不到路由
+        let (status, _, _) = send(
+            &state,
+            get(
+                "/api/v1/projects/app/envs/regions/us-east/configs",
+                Some("k"),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_etag_only_on_full_config() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let checksum = state
+            .center
+            .load()
+            .config_checksum("app", "default")
+            .unwrap();
+        let configs = "/api/v1/projects/app/envs/default/configs";
+
+        let (_, headers, _) = send(&state, get(configs, Some("k"))).await;
+        assert_eq!(headers[header::ETAG], format!("W/\"{}\"", checksum));
+        assert_eq!(headers[header::VARY], "Accept, Accept-Encoding");
+
+        // 子集、展开后的内容与完整配置不同，不能共用同一个 ETag
+        for query in ["?keys=a", "?flat=true"] {
+            let uri = format!("{}{}", configs, query);
+            let (status, headers, _) = send(&state, get(&uri, Some("k"))).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(headers.get(header::ETAG).is_none(), "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_configs() {
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let head = |uri: &str| {
+            Request::builder()
+                .method("HEAD")
+                .uri(uri)
+                .header("X-API-Key", "k")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (_, get_headers, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs", Some("k")),
+        )
+        .await;
+        let (status, headers, body) =
+            send(&state, head("/api/v1/projects/app/envs/default/configs")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.is_empty());
+        assert_eq!(headers[header::ETAG], get_headers[header::ETAG]);
+        assert_eq!(headers["X-Config-Revision"], "0");
+
+        // HEAD 只检查新鲜度，不计为读取
+        let (_, _, metrics) = send(&state, get("/metrics", None)).await;
+        assert!(metrics.contains("configai_config_reads_total{env=\"default\",project=\"app\"} 1"));
+        let (_, _, single) =
+            send(&state, head("/api/v1/projects/app/envs/default/configs/a")).await;
+        assert!(single.is_empty());
+        let (_, _, metrics) = send(&state, get("/metrics", None)).await;
+        assert!(metrics.contains("configai_config_reads_total{env=\"default\",project=\"app\"} 1"));
+
+        let (status, _, body) =
+            send(&state, head("/api/v1/projects/app/envs/missing/configs")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_error_codes() {
+        let cases = [
+            (
+                ConfigError::ProjectNotFound("p".into()),
+                404,
+                "PROJECT_NOT_FOUND",
+            ),
+            (
+                ConfigError::EnvironmentNotFound("e".into()),
+                404,
+                "ENVIRONMENT_NOT_FOUND",
+            ),
+            (
+                ConfigError::ConfigItemNotFound("k".into()),
+                404,
+                "CONFIG_ITEM_NOT_FOUND",
+            ),
+            (
+                ConfigError::SharedGroupNotFound("g".into()),
+                404,
+                "SHARED_GROUP_NOT_FOUND",
+            ),
+            (ConfigError::InvalidKey("k".into()), 500, "INVALID_KEY"),
+            (
+                ConfigError::InvalidParameter("limit".into()),
+                400,
+                "INVALID_PARAMETER",
+            ),
+            (ConfigError::KeyConflict("k".into()), 500, "KEY_CONFLICT"),
+            (
+                ConfigError::FileReference("f".into()),
+                500,
+                "FILE_REFERENCE",
+            ),
+            (ConfigError::Template("t".into()), 500, "TEMPLATE"),
+            (ConfigError::Unauthorized("u".into()), 401, "UNAUTHORIZED"),
+            (ConfigError::Forbidden("f".into()), 403, "FORBIDDEN"),
+            (ConfigError::RateLimited(1), 429, "RATE_LIMITED"),
+            (ConfigError::StorageError("s".into()), 500, "STORAGE_ERROR"),
+            (
+                ConfigError::IoError(std::io::Error::other("disk")),
+                500,
+                "IO_ERROR",
+            ),
+        ];
+        for (error, status, code) in cases {
+            let message = error.to_string();
+            let response = error.into_response();
+            assert_eq!(response.status().as_u16(), status, "{}", code);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code);
+            assert_eq!(json["error"], message);
+        }
+
+        // 经过路由的错误同样带 code
+        let tmp = TempDir::new().unwrap();
+        write_config(tmp.path(), 1);
+        let state = AppState::new(ConfigCenter::new(tmp.path()).unwrap());
+        let (_, _, body) = send(
+            &state,
+            get("/api/v1/projects/app/envs/missing/configs", Some("k")),
+        )
+        .await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["code"], "ENVIRONMENT_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_validate_endpoint() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("shared")).unwrap();
+        std::fs::write(base.join("shared/default.yaml"), "a: x\n").unwrap();
+        std::fs::write(base.join("projects/app/broken.yaml"), "a-b: 1\na_b: 2\n").unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+        let validate = |env: &str, key: &str| {
+            get(
+                &format!("/api/v1/projects/app/envs/{}/validate", env),
+                Some(key),
+            )
+        };
+
+        let (status, _, body) = send(&state, validate("broken", "admin")).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["valid"], false);
+        let kinds: Vec<&str> = json["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["kind"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, vec!["type_conflict", "env_key_collision"]);
+
+        let (status, _, _) = send(&state, validate("broken", "k")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, _) = send(&state, validate("missing", "admin")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_flat_output() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "db:\n  host: localhost\nhosts: [a, b]\n",
+        )
+        .unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        let (status, _, body) = send(
+            &state,
+            get(
+                "/api/v1/projects/app/envs/default/configs?flat=true",
+                Some("k"),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json["configs"],
+            serde_json::json!({"db.host": "localhost", "hosts.0": "a", "hosts.1": "b"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_projects_scoped_to_key() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("projects/other")).unwrap();
+        std::fs::write(
+            base.join("projects/other/project.yaml"),
+            "description: other app\napi_keys:\n  - key: other-key\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/other/default.yaml"), "x: 1\n").unwrap();
+        std::fs::write(base.join("projects/other/production.yaml"), "x: 2\n").unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        let (status, _, body) = send(&state, get("/api/v1/projects", Some("other-key"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json["items"],
+            serde_json::json!([{
+                "name": "other",
+                "description": "other app",
+                "tags": [],
+                "environments": ["default", "production"]
+            }])
+        );
+        assert_eq!(json["total"], 1);
+
+        let (status, _, _) = send(&state, get("/api/v1/projects", None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _, _) = send(&state, get("/api/v1/projects", Some("bad"))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_list_projects_filtered_by_tag() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\ntags: [\"team:payments\", \"tier:prod\"]\n",
+        )
+        .unwrap();
+        for (name, tags) in [("billing", "[\"team:payments\"]"), ("search", "[]")] {
+            std::fs::create_dir_all(base.join("projects").join(name)).unwrap();
+            std::fs::write(
+                base.join("projects").join(name).join("project.yaml"),
+                format!("tags: {}\n", tags),
+            )
+            .unwrap();
+        }
+        std::fs::create_dir_all(base.join("projects/legacy")).unwrap();
+        std::fs::write(base.join("projects/legacy/project.yaml"), "api_keys: []\n").unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+
+        let names = |body: &str| -> Vec<String> {
+            let json: serde_json::Value = serde_json::from_str(body).unwrap();
+            json["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| p["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let (status, _, body) = send(
+            &state,
+            get("/api/v1/projects?tag=team:payments", Some("admin")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&body), vec!["app", "billing"]);
+
+        // 未声明 tags 的项目默认为空列表
+        let (_, _, body) = send(&state, get("/api/v1/projects", Some("admin"))).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let legacy = json["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "legacy")
+            .unwrap();
+        assert_eq!(legacy["tags"], serde_json::json!([]));
+
+        // 项目 Key 只在自己的项目内过滤
+        let (_, _, body) = send(&state, get("/api/v1/projects?tag=tier:prod", Some("k"))).await;
+        assert_eq!(names(&body), vec!["app"]);
+        let (_, _, body) = send(&state, get("/api/v1/projects?tag=tier:staging", Some("k"))).await;
+        assert!(names(&body).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archived_project_hidden() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("projects/legacy")).unwrap();
+        std::fs::write(
+            base.join("projects/legacy/project.yaml"),
+            "archived: true\napi_keys:\n  - key: legacy-key\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/legacy/default.yaml"), "x: 1\n").unwrap();
+        let load = || {
+            ConfigCenter::new(base)
+                .unwrap()
+                .with_admin_key(Some("admin".to_string()))
+        };
+        let state = AppState::new(load());
+        let names = |body: &str| -> Vec<String> {
+            let json: serde_json::Value = serde_json::from_str(body).unwrap();
+            json["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| p["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let (_, _, body) = send(&state, get("/api/v1/projects", Some("admin"))).await;
+        assert_eq!(names(&body), vec!["app"]);
+        let (_, _, body) = send(&state, get("/api/v1/projects?archived=true", Some("admin"))).await;
+        assert_eq!(names(&body), vec!["app", "legacy"]);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["items"][1]["archived"], true);
+        assert!(json["items"][0].get("archived").is_none());
+
+        for key in ["admin", "legacy-key"] {
+            let (status, _, _) = send(
+                &state,
+                get("/api/v1/projects/legacy/envs/default/configs", Some(key)),
+            )
+            .await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+
+        // 去掉归档标记后恢复访问
+        std::fs::write(
+            base.join("projects/legacy/project.yaml"),
+            "api_keys:\n  - key: legacy-key\n",
+        )
+        .unwrap();
+        state.center.store(Arc::new(load()));
+        let (status, _, _) = send(
+            &state,
+            get(
+                "/api/v1/projects/legacy/envs/default/configs",
+                Some("legacy-key"),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, _, body) = send(&state, get("/api/v1/projects", Some("admin"))).await;
+        assert_eq!(names(&body), vec!["app", "legacy"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_all_environments_admin_only() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(base.join("projects/app/prod.yaml"), "a: 2\n").unwrap();
+        std::fs::create_dir_all(base.join("projects/billing")).unwrap();
+        std::fs::write(base.join("projects/billing/default.yaml"), "x: 1\n").unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+
+        let (status, _, body) = send(&state, get("/api/v1/environments", Some("admin"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json["items"],
+            serde_json::json!([
+                {"project": "app", "environment": "default"},
+                {"project": "app", "environment": "prod"},
+                {"project": "billing", "environment": "default"}
+            ])
+        );
+
+        let (status, _, _) = send(&state, get("/api/v1/environments", Some("k"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        for name in ["a", "b", "c", "d", "e"] {
+            std::fs::create_dir_all(base.join("projects").join(name)).unwrap();
+            std::fs::write(
+                base.join("projects").join(name).join("default.yaml"),
+                "x: 1\n",
+            )
+            .unwrap();
+        }
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+        let page = |uri: &str| {
+            let state = state.clone();
+            let uri = uri.to_string();
+            async move {
+                let (status, _, body) = send(&state, get(&uri, Some("admin"))).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).ok(),
+                )
+            }
+        };
+
+        let (status, json) = page("/api/v1/projects").await;
+        assert_eq!(status, StatusCode::OK);
+        let json = json.unwrap();
+        assert_eq!(json["items"].as_array().unwrap().len(), 5);
+        assert_eq!(json["limit"], 100);
+        assert_eq!(json["offset"], 0);
+
+        let (_, json) = page("/api/v1/projects?limit=2&offset=2").await;
+        let json = json.unwrap();
+        let names: Vec<_> = json["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["c", "d"]);
+        assert_eq!(json["total"], 5);
+
+        let (_, json) = page("/api/v1/environments?limit=2&offset=4").await;
+        let json = json.unwrap();
+        assert_eq!(
+            json["items"],
+            serde_json::json!([{"project": "e", "environment": "default"}])
+        );
+
+        // offset 超出末尾：空列表，total 不变
+        let (status, json) = page("/api/v1/environments?offset=10").await;
+        assert_eq!(status, StatusCode::OK);
+        let json = json.unwrap();
+        assert_eq!(json["items"], serde_json::json!([]));
+        assert_eq!(json["total"], 5);
+
+        for bad in ["limit=0", "limit=1001", "limit=-1", "offset=-1"] {
+            let (status, _) = page(&format!("/api/v1/projects?{}", bad)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_shared_environments_admin_only() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("shared/org")).unwrap();
+        std::fs::write(base.join("shared/default.yaml"), "x: 1\n").unwrap();
+        std::fs::write(base.join("shared/prod.yaml"), "x: 2\n").unwrap();
+        std::fs::write(base.join("shared/org/default.yaml"), "y: 1\n").unwrap();
+        // shared/ 子目录只有被项目引用时才是共享组
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\nshared_groups: [org]\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+
+        let (status, _, body) = send(&state, get("/api/v1/shared", Some("admin"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "environments": ["default", "prod"],
+                "groups": {"org": ["default"]}
+            })
+        );
+
+        let (status, _, _) = send(&state, get("/api/v1/shared", Some("k"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_list_environments() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(base.join("projects/app/staging.yaml"), "a: 2\n").unwrap();
+        std::fs::create_dir_all(base.join("projects/other")).unwrap();
+        std::fs::write(
+            base.join("projects/other/project.yaml"),
+            "api_keys:\n  - key: other-key\n",
+        )
+        .unwrap();
+        let state = AppState::new(ConfigCenter::new(base).unwrap());
+
+        let (status, _, body) = send(&state, get("/api/v1/projects/app/envs", Some("k"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"project": "app", "environments": ["default", "staging"]})
+        );
+
+        let (status, _, _) =
+            send(&state, get("/api/v1/projects/app/envs", Some("other-key"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, headers, body) = send(
+            &state,
+            get("/api/v1/projects/app/envs?format=ndjson", Some("k")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/x-ndjson");
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            serde_json::json!({"env": "staging", "config": {"a": 2, "b": 1}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_key_cross_project_access() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::create_dir_all(base.join("projects/other")).unwrap();
+        std::fs::write(
+            base.join("projects/other/project.yaml"),
+            "api_keys:\n  - key: other-key\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/other/default.yaml"), "x: 1\n").unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let state = AppState::new(center);
+
+        for project in ["app", "other"] {
+            let uri = format!("/api/v1/projects/{}/envs/default/configs", project);
+            let (status, _, _) = send(&state, get(&uri, Some("admin"))).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        // 项目 Key 仍只能访问自己的项目
+        let (status, _, _) = send(
+            &state,
+            get("/api/v1/projects/other/envs/default/configs", Some("k")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (_, _, body) = send(&state, get("/api/v1/projects", Some("admin"))).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let names: Vec<&str> = json["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["app", "other"]);
+
+        let (_, _, body) = send(&state, get("/api/v1/projects", Some("k"))).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["items"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_records_reads() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_config(base, 1);
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n    label: ci-runner\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base)
+            .unwrap()
+            .with_admin_key(Some("admin".to_string()));
+        let audit_file = base.join("audit.log");
+        let state =
+            AppState::new(center).with_audit_log(AuditLog::new(10).with_file(&audit_file).unwrap());
+
+        let (status, _, _) = send(
+            &state,
+            get("/api/v1/projects/app/envs/default/configs/a", Some("k")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // 失败的读取不记录
+        let (status, _, _) = send(
+            &state,
+            get(
+                "/api/v1/projects/app/envs/default/configs/missing",
+                Some("k"),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let entries = state.audit.recent(10);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.key_id, "ci-runner");
+        assert_eq!(entry.project, "app");
+        assert_eq!(entry.environment, "default");
+        assert_eq!(entry.key, "a");
+        assert!(entry.timestamp > 0);
+
+        let lines = std::fs::read_to_string(&audit_file).unwrap();
+        assert_eq!(lines.lines().count(), 1);
+        assert!(!lines.contains("\"k\""));
+
+        // 仅管理员可查看
+        let (status, _, _) = send(&state, get("/api/v1/audit", Some("k"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, body) = send(&state, get("/api/v1/audit", Some("admin"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json[0]["key_id"], "ci-runner");
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,79 @@
+use prometheus::{Encoder, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+use crate::core::ConfigCenter;
+
+/// Prometheus 指标（跨配置重载保留）
+pub struct Metrics {
+    registry: Registry,
+    /// HTTP 请求总数
+    pub requests: IntCounter,
+    /// 认证失败次数（401/403）
+    pub auth_failures: IntCounter,
+    /// 各项目、环境的配置读取次数。只在读取成功后计数，
+    /// 标签取值限于已加载的项目和环境（NDJSON 整体导出记为 `*`），基数有界
+    pub config_reads: IntCounterVec,
+    /// 配置重载次数
+    pub reloads: IntCounter,
+    projects: IntGauge,
+    environments: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests =
+            IntCounter::new("configai_http_requests_total", "Total HTTP requests").unwrap();
+        let auth_failures = IntCounter::new(
+            "configai_auth_failures_total",
+            "Requests rejected by API key validation",
+        )
+        .unwrap();
+        let config_reads = IntCounterVec::new(
+            Opts::new(
+                "configai_config_reads_total",
+                "Config reads per project and environment",
+            ),
+            &["project", "env"],
+        )
+        .unwrap();
+        let reloads = IntCounter::new("configai_reloads_total", "Config reloads").unwrap();
+        let projects = IntGauge::new("configai_projects", "Loaded projects").unwrap();
+        let environments =
+            IntGauge::new("configai_environments", "Loaded project environments").unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(auth_failures.clone())).unwrap();
+        registry.register(Box::new(config_reads.clone())).unwrap();
+        registry.register(Box::new(reloads.clone())).unwrap();
+        registry.register(Box::new(projects.clone())).unwrap();
+        registry.register(Box::new(environments.clone())).unwrap();
+
+        Self {
+            registry,
+            requests,
+            auth_failures,
+            config_reads,
+            reloads,
+            projects,
+            environments,
+        }
+    }
+
+    /// 以 Prometheus 文本格式输出；项目/环境数量在输出前按当前快照刷新
+    pub fn render(&self, center: &ConfigCenter) -> String {
+        self.projects.set(center.list_projects().len() as i64);
+        self.environments.set(center.environment_count() as i64);
+
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .unwrap();
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
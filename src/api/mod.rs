@@ -1,5 +1,8 @@
+pub mod audit;
 pub mod handlers;
+pub mod metrics;
+pub mod rate_limit;
 pub mod routes;
 
 pub use handlers::AppState;
-pub use routes::create_router;
+pub use routes::{create_router, create_router_with, RouterOptions, DEFAULT_REQUEST_TIMEOUT};
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::error::ConfigError;
+
+/// 按 API Key 的令牌桶限流器
+pub struct RateLimiter {
+    /// 每秒补充的令牌数
+    rate: f64,
+    /// 桶容量（允许的突发请求数）
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst: burst.max(1.0),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 为 key 消耗一个令牌；桶空时返回 RateLimited（附带建议重试秒数）
+    pub fn check(&self, key: &str) -> Result<(), ConfigError> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), ConfigError> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after = if self.rate > 0.0 {
+            ((1.0 - bucket.tokens) / self.rate).ceil().max(1.0) as u64
+        } else {
+            u64::MAX
+        };
+        Err(ConfigError::RateLimited(retry_after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let limiter = RateLimiter::new(2.0, 2.0);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("k", start).is_ok());
+        assert!(limiter.check_at("k", start).is_ok());
+        let err = limiter.check_at("k", start).unwrap_err();
+        assert!(matches!(err, ConfigError::RateLimited(1)));
+
+        // 其它 key 不受影响
+        assert!(limiter.check_at("other", start).is_ok());
+
+        // 0.5 秒后补充 1 个令牌
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at("k", later).is_ok());
+        assert!(limiter.check_at("k", later).is_err());
+    }
+}
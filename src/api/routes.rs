@@ -1,16 +1,79 @@
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::Router;
+use std::time::Duration;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::timeout::TimeoutLayer;
+use tracing::Instrument;
 
-use super::handlers::{export_env, get_all_configs, get_single_config, AppState};
+use crate::error::ConfigError;
 
-/// 创建 API 路由
+use super::handlers::{
+    export_env, get_all_configs, get_checksum, get_single_config, health, list_all_environments,
+    list_audit, list_environments, list_projects, list_shared_environments, metrics, reload_status,
+    validate_env, AppState,
+};
+
+/// 响应体小于该字节数时不压缩
+const COMPRESSION_MIN_SIZE: u16 = 1024;
+
+/// 默认单个请求的超时时间
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 路由级选项（启动时确定）
+#[derive(Debug, Clone)]
+pub struct RouterOptions {
+    /// 允许跨域访问的 Origin；为空则不启用 CORS，`*` 表示任意来源
+    pub cors_origins: Vec<String>,
+    /// 单个请求的处理时限，超时返回 503
+    pub request_timeout: Duration,
+    /// 只读模式：任何写方法（POST/PUT/PATCH/DELETE 等）在路由层直接返回 403
+    pub read_only: bool,
+}
+
+impl Default for RouterOptions {
+    fn default() -> Self {
+        Self {
+            cors_origins: Vec::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            read_only: false,
+        }
+    }
+}
+
+/// 创建 API 路由（默认选项）
 pub fn create_router(state: AppState) -> Router {
-    Router::new()
-        .route("/health", get(|| async { "ok" }))
+    create_router_with(state, &RouterOptions::default())
+}
+
+/// 按选项创建 API 路由
+pub fn create_router_with(state: AppState, options: &RouterOptions) -> Router {
+    let router = Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .route("/api/v1/audit", get(list_audit))
+        .route("/api/v1/environments", get(list_all_environments))
+        .route("/api/v1/shared", get(list_shared_environments))
+        .route("/api/v1/reload-status", get(reload_status))
+        .route("/api/v1/projects", get(list_projects))
+        .route("/api/v1/projects/{project}/envs", get(list_environments))
         .route(
             "/api/v1/projects/{project}/envs/{env}/configs",
             get(get_all_configs),
         )
+        .route(
+            "/api/v1/projects/{project}/envs/{env}/checksum",
+            get(get_checksum),
+        )
+        .route(
+            "/api/v1/projects/{project}/envs/{env}/validate",
+            get(validate_env),
+        )
         .route(
             "/api/v1/projects/{project}/envs/{env}/configs/{key}",
             get(get_single_config),
@@ -18,6 +81,235 @@ pub fn create_router(state: AppState) -> Router {
         .route(
             "/api/v1/projects/{project}/envs/{env}/export",
             get(export_env),
+        );
+    with_layers(router, state, options)
+}
+
+/// 为路由附加超时、计数、压缩、日志等中间件
+fn with_layers(router: Router<AppState>, state: AppState, options: &RouterOptions) -> Router {
+    // 处理超时是服务端的问题：408 表示客户端发送请求太慢，这里用 503
+    let mut router = router.layer(TimeoutLayer::with_status_code(
+        StatusCode::SERVICE_UNAVAILABLE,
+        options.request_timeout,
+    ));
+    if options.read_only {
+        router = router.layer(middleware::from_fn(reject_writes));
+    }
+    let mut router = router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            count_requests,
+        ))
+        .layer(
+            CompressionLayer::new()
+                .gzip(true)
+                .deflate(true)
+                .compress_when(DefaultPredicate::new().and(SizeAbove::new(COMPRESSION_MIN_SIZE))),
+        )
+        .layer(middleware::from_fn(log_requests))
+        .layer(middleware::map_response(version_header));
+
+    if let Some(cors) = cors_layer(&options.cors_origins) {
+        router = router.layer(cors);
+    }
+
+    router.with_state(state)
+}
+
+/// 构建 CORS 层：允许 GET 读取及携带 X-API-Key 或 Authorization 的预检请求
+fn cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(origins.iter().filter_map(|o| HeaderValue::from_str(o).ok()))
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::HEAD, Method::OPTIONS])
+            .allow_headers([
+                HeaderName::from_static("x-api-key"),
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+            ]),
+    )
+}
+
+/// 请求日志中间件：为每个请求生成 request ID，写入 tracing span 并通过 X-Request-Id 回传
+async fn log_requests(request: Request, next: Next) -> Response {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let span = tracing::info_span!("request", request_id = %request_id);
+
+    async move {
+        let method = request.method().clone();
+        let uri = request.uri().clone();
+        let started = std::time::Instant::now();
+        tracing::info!("--> {} {}", method, uri);
+
+        let mut response = next.run(request).await;
+
+        tracing::info!(
+            "<-- {} {} {} ({:?})",
+            method,
+            uri,
+            response.status().as_u16(),
+            started.elapsed()
+        );
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert("X-Request-Id", value);
+        }
+        response
+    }
+    .instrument(span)
+    .await
+}
+
+/// 为每个响应附加 X-Configai-Version，便于混合版本发布时定位服务实例
+async fn version_header(mut response: Response) -> Response {
+    response.headers_mut().insert(
+        "X-Configai-Version",
+        HeaderValue::from_static(env!("CARGO_PKG_VERSION")),
+    );
+    response
+}
+
+/// 只读模式中间件：放行 GET/HEAD/OPTIONS，其余方法一律拒绝，
+/// 保证即使以后加入写接口，只读副本也不会被修改
+async fn reject_writes(request: Request, next: Next) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+    ConfigError::Forbidden(format!(
+        "server is read-only: {} not allowed",
+        request.method()
+    ))
+    .into_response()
+}
+
+/// 请求计数中间件
+async fn count_requests(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    state.metrics.requests.inc();
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let state = AppState::new(crate::core::ConfigCenter::new(tmp.path()).unwrap());
+        let slow = Router::new().route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "done"
+            }),
+        );
+        let options = RouterOptions {
+            request_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let response = with_layers(slow, state, &options)
+            .oneshot(Request::builder().uri("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_rejects_writes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let state = AppState::new(crate::core::ConfigCenter::new(tmp.path()).unwrap());
+        let routes = || {
+            Router::new().route(
+                "/items",
+                get(|| async { "list" }).post(|| async { "created" }),
+            )
+        };
+        let send = |read_only: bool, method: &str| {
+            let options = RouterOptions {
+                read_only,
+                ..Default::default()
+            };
+            with_layers(routes(), state.clone(), &options).oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri("/items")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = send(true, "POST").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(true, "GET").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(false, "POST").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// 收集日志输出的 writer
+    #[derive(Clone, Default)]
+    struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_key_not_logged() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("projects/app")).unwrap();
+        std::fs::write(
+            tmp.path().join("projects/app/project.yaml"),
+            "api_keys:\n  - key: secret-key-value\n",
         )
-        .with_state(state)
+        .unwrap();
+        std::fs::write(tmp.path().join("projects/app/default.yaml"), "a: 1\n").unwrap();
+        let state = AppState::new(crate::core::ConfigCenter::new(tmp.path()).unwrap());
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        for key in ["secret-key-value", "wrong-key-value"] {
+            create_router(state.clone())
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/v1/projects/app/envs/default/configs")
+                        .header("X-API-Key", key)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let logs = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("/api/v1/projects/app/envs/default/configs"));
+        assert!(!logs.contains("secret-key-value"));
+        assert!(!logs.contains("wrong-key-value"));
+    }
 }
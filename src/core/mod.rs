@@ -1,82 +1,651 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
 
 use crate::error::{ConfigError, Result};
-use crate::storage::Storage;
+use crate::models::{ApiKeyEntry, ConfigState, ProjectMeta};
+use crate::storage::{LoadOptions, SourceFile, Storage};
+
+type MergedConfig = HashMap<String, serde_json::Value>;
+
+/// 两个环境合并结果的差异（以 a 为基准）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EnvDiff {
+    /// 仅在 b 中存在
+    pub added: HashMap<String, serde_json::Value>,
+    /// 仅在 a 中存在
+    pub removed: HashMap<String, serde_json::Value>,
+    /// 两边都存在但值不同（深比较）
+    pub changed: HashMap<String, ValueChange>,
+}
+
+impl EnvDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+/// 最近一次加载/重载的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReloadStatus {
+    /// Unix 时间戳（秒）
+    pub timestamp: u64,
+    pub success: bool,
+    /// 失败原因（成功时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReloadStatus {
+    fn now(error: Option<String>) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            timestamp,
+            success: error.is_none(),
+            error,
+        }
+    }
+}
+
+/// 环境校验发现的问题
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+/// 校验问题的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// `${VAR}` 引用的环境变量未设置
+    UnresolvedVar,
+    /// 合并失败（如 `${file:...}` 无法读取、替换后 key 冲突）
+    MergeError,
+    /// 不同配置 key 转成同一个环境变量名
+    EnvKeyCollision,
+    /// 覆盖值与被覆盖值类型不同
+    TypeConflict,
+    /// key 含不允许的字符
+    InvalidKey,
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// 环境变量名前缀
+    pub prefix: Option<String>,
+    /// 只导出以此开头的配置 key（匹配转换前的原始 key，区分大小写）
+    pub key_prefix: Option<String>,
+    /// 不导出的配置 key（原始 key）
+    pub exclude: Vec<String>,
+    /// 不导出以此开头的配置 key；排除优先于 `key_prefix`
+    pub exclude_prefix: Option<String>,
+    /// 导出前渲染 Tera 模板值
+    pub render: bool,
+    /// 数组值的序列化方式
+    pub array_format: ArrayFormat,
+    /// `ArrayFormat::Csv` 的分隔符，默认 `,`
+    pub separator: Option<String>,
+    /// 输出的 shell 语法
+    pub format: ExportFormat,
+    /// 布尔值的输出方式
+    pub bool_format: BoolFormat,
+    /// null 值的输出方式
+    pub null_as: NullFormat,
+}
+
+/// 导出时 null 值的输出方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NullFormat {
+    /// 空字符串
+    #[default]
+    Empty,
+    /// 不输出该 key
+    Omit,
+    /// 字面量 `null`
+    Literal,
+}
+
+/// 导出时布尔值的输出方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoolFormat {
+    /// `true` / `false`
+    #[default]
+    Literal,
+    /// `1` / `0`
+    Numeric,
+}
+
+/// 导出的 shell 语法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// POSIX shell：`export KEY=value`
+    #[default]
+    Shell,
+    /// PowerShell：`$env:KEY = "value"`
+    Powershell,
+    /// Windows cmd：`set KEY=value`
+    Cmd,
+    /// fish：`set -gx KEY value`
+    Fish,
+}
+
+/// 导出时数组值的序列化方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrayFormat {
+    /// 原始 JSON：`["a","b"]`
+    #[default]
+    Json,
+    /// 元素均为标量时用分隔符连接：`a,b`；含对象/数组的仍输出 JSON
+    Csv,
+}
 
 /// 配置中心：只读，从 YAML 目录加载
 pub struct ConfigCenter {
     storage: Storage,
+    /// 修订号：每次重新加载递增
+    revision: u64,
+    /// 合并结果缓存：(项目, 环境) -> 合并后的配置，仅在当前修订号内有效。
+    /// 环境变量替换结果在首次读取时固定，进程环境变化需 reload 后生效；
+    /// 引用了 `${file:...}`/`@file:` 的结果不缓存，轮换后的密钥文件在下次读取时生效。
+    merged_cache: Mutex<HashMap<(String, String), Arc<MergedConfig>>>,
+    /// 全局管理员 Key：可访问任意项目，由启动参数/环境变量注入，不随目录重载变化
+    admin_key: Option<String>,
+    /// `${file:...}` 允许读取的目录；未设置时禁用文件引用
+    secrets_dir: Option<PathBuf>,
+    /// 最近一次重载结果：构建时为成功，重载失败时由调用方记录（当前实例继续提供服务）
+    last_reload: Mutex<ReloadStatus>,
 }
 
 impl ConfigCenter {
     pub fn new(config_dir: &Path) -> Result<Self> {
-        let storage = Storage::load(config_dir)?;
-        Ok(Self { storage })
+        Self::with_options(config_dir, &LoadOptions::default())
+    }
+
+    pub fn with_options(config_dir: &Path, options: &LoadOptions) -> Result<Self> {
+        let storage = Storage::load_with(config_dir, options)?;
+        Ok(Self::from_storage(storage, 0))
+    }
+
+    /// 由内存中的状态构建（不读写磁盘）
+    pub fn from_state(state: ConfigState) -> Self {
+        Self::from_storage(Storage::from_state(state), 0)
+    }
+
+    fn from_storage(storage: Storage, revision: u64) -> Self {
+        Self {
+            storage,
+            revision,
+            merged_cache: Mutex::new(HashMap::new()),
+            admin_key: None,
+            secrets_dir: None,
+            last_reload: Mutex::new(ReloadStatus::now(None)),
+        }
+    }
+
+    /// 最近一次加载/重载的时间和结果
+    pub fn last_reload(&self) -> ReloadStatus {
+        self.last_reload.lock().unwrap().clone()
+    }
+
+    /// 记录一次失败的重载：配置保持不变，只更新状态
+    pub fn record_reload_failure(&self, error: &ConfigError) {
+        *self.last_reload.lock().unwrap() = ReloadStatus::now(Some(error.to_string()));
+    }
+
+    /// 设置全局管理员 Key（空字符串视为未设置）
+    pub fn with_admin_key(mut self, key: Option<String>) -> Self {
+        self.admin_key = key.filter(|k| !k.is_empty());
+        self
     }
 
+    /// 设置 `${file:...}` 允许读取的目录
+    pub fn with_secrets_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.secrets_dir = dir;
+        self
+    }
+
+    /// 是否为全局管理员 Key
+    pub fn is_admin_key(&self, key: &str) -> bool {
+        self.admin_key.as_deref() == Some(key)
+    }
+
+    /// 按首次加载时的选项重新加载
     pub fn reload(&mut self, config_dir: &Path) -> Result<()> {
-        self.storage = Storage::load(config_dir)?;
+        self.storage = Storage::load_with(config_dir, self.storage.options())?;
+        self.revision += 1;
+        self.merged_cache.lock().unwrap().clear();
         Ok(())
     }
 
+    /// 以相同来源和选项构建新实例，修订号递增（用于整体替换）
+    pub fn reloaded(&self) -> Result<Self> {
+        let storage = self.storage.reload()?;
+        Ok(Self::from_storage(storage, self.revision + 1)
+            .with_admin_key(self.admin_key.clone())
+            .with_secrets_dir(self.secrets_dir.clone()))
+    }
+
+    /// 同 `reloaded`，但只重新扫描 `changed` 涉及的项目（见 `Storage::reload_paths`）
+    pub fn reloaded_paths(&self, changed: &[PathBuf]) -> Result<Self> {
+        let storage = self.storage.reload_paths(changed)?;
+        Ok(Self::from_storage(storage, self.revision + 1)
+            .with_admin_key(self.admin_key.clone())
+            .with_secrets_dir(self.secrets_dir.clone()))
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 加载过程中被跳过的文件（仅宽松模式）
+    pub fn load_warnings(&self) -> &[String] {
+        self.storage.warnings()
+    }
+
+    /// 项目名列表（按名称排序）
     pub fn list_projects(&self) -> Vec<&str> {
-        self.storage
+        let mut projects: Vec<&str> = self
+            .storage
             .state()
             .projects
             .keys()
             .map(|s| s.as_str())
-            .collect()
+            .collect();
+        projects.sort();
+        projects
+    }
+
+    /// 全局共享配置的环境名（按名称排序）
+    pub fn list_shared_environments(&self) -> Vec<&str> {
+        let mut envs: Vec<&str> = self
+            .storage
+            .state()
+            .shared
+            .keys()
+            .map(|s| s.as_str())
+            .collect();
+        envs.sort();
+        envs
+    }
+
+    /// 命名共享组的环境名（按名称排序）
+    pub fn list_shared_group_environments(&self, group: &str) -> Result<Vec<&str>> {
+        let envs = self
+            .storage
+            .state()
+            .shared_groups
+            .get(group)
+            .ok_or_else(|| ConfigError::SharedGroupNotFound(group.to_string()))?;
+        let mut names: Vec<&str> = envs.keys().map(|s| s.as_str()).collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// 命名共享组列表（按名称排序）
+    pub fn list_shared_groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = self
+            .storage
+            .state()
+            .shared_groups
+            .keys()
+            .map(|s| s.as_str())
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    /// 项目元信息
+    pub fn project_meta(&self, project: &str) -> Result<&ProjectMeta> {
+        self.storage
+            .state()
+            .projects
+            .get(project)
+            .map(|p| &p.meta)
+            .ok_or_else(|| ConfigError::ProjectNotFound(project.to_string()))
+    }
+
+    /// 项目是否已归档（项目不存在时为 false）
+    pub fn is_archived(&self, project: &str) -> bool {
+        self.project_meta(project).is_ok_and(|meta| meta.archived)
+    }
+
+    /// 项目的环境名列表（按名称排序）
+    pub fn list_environments(&self, project: &str) -> Result<Vec<&str>> {
+        let proj = self
+            .storage
+            .state()
+            .projects
+            .get(project)
+            .ok_or_else(|| ConfigError::ProjectNotFound(project.to_string()))?;
+        let mut envs: Vec<&str> = proj.environments.keys().map(|s| s.as_str()).collect();
+        envs.sort();
+        Ok(envs)
+    }
+
+    /// 所有 (项目, 环境) 组合，按项目名、环境名排序
+    pub fn list_all_environments(&self) -> Vec<(String, String)> {
+        let mut all: Vec<(String, String)> = self
+            .storage
+            .state()
+            .projects
+            .iter()
+            .flat_map(|(project, data)| {
+                data.environments
+                    .keys()
+                    .map(move |env| (project.clone(), env.clone()))
+            })
+            .collect();
+        all.sort();
+        all
+    }
+
+    /// 所有项目的环境总数
+    pub fn environment_count(&self) -> usize {
+        self.storage
+            .state()
+            .projects
+            .values()
+            .map(|p| p.environments.len())
+            .sum()
+    }
+
+    /// 已加载的配置项总数（各项目各环境、共享配置及共享分组的顶层 key，合并前）
+    pub fn config_item_count(&self) -> usize {
+        let state = self.storage.state();
+        let project_items: usize = state
+            .projects
+            .values()
+            .flat_map(|p| p.environments.values())
+            .map(|env| env.len())
+            .sum();
+        let shared_items: usize = state.shared.values().map(|env| env.len()).sum();
+        let group_items: usize = state
+            .shared_groups
+            .values()
+            .flat_map(|g| g.values())
+            .map(|env| env.len())
+            .sum();
+        project_items + shared_items + group_items
     }
 
     /// 合并配置：shared[env] 为底，project[env] 覆盖
-    pub fn get_merged_config(
+    pub fn get_merged_config(&self, project: &str, env: &str) -> Result<MergedConfig> {
+        Ok(self.merged(project, env)?.as_ref().clone())
+    }
+
+    /// 合并配置的 SHA-256（十六进制）。各层对象都按 key 排序后序列化，与 map 顺序无关，
+    /// 可用于多副本间比对配置是否一致
+    pub fn config_checksum(&self, project: &str, env: &str) -> Result<String> {
+        Ok(checksum(self.merged(project, env)?.as_ref()))
+    }
+
+    /// 参与合并的来源文件，顺序同合并层（低优先级在前）；纯内存存储为空
+    pub fn source_files(&self, project: &str, env: &str) -> Result<Vec<&SourceFile>> {
+        // 同时校验项目和环境存在
+        self.merge_layers(project, env)?;
+        let meta = self.project_meta(project)?;
+        let mut scopes = vec!["shared".to_string()];
+        scopes.extend(meta.shared_groups.iter().map(|g| format!("shared/{}", g)));
+        scopes.push(format!("projects/{}", project));
+        let envs: &[&str] = if env == "default" {
+            &["default"]
+        } else {
+            &["default", env]
+        };
+        Ok(scopes
+            .iter()
+            .flat_map(|scope| envs.iter().map(move |e| format!("{}/{}", scope, e)))
+            .filter_map(|layer| self.storage.source(&layer))
+            .collect())
+    }
+
+    /// 来源文件相对配置目录的路径（如 `shared/prod.yaml`），顺序同合并层，用于排查配置来源
+    pub fn source_paths(&self, project: &str, env: &str) -> Result<Vec<String>> {
+        let root = self.storage.config_dir();
+        Ok(self
+            .source_files(project, env)?
+            .iter()
+            .map(|f| {
+                let path = root
+                    .and_then(|r| f.path.strip_prefix(r).ok())
+                    .unwrap_or(&f.path);
+                path.components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect())
+    }
+
+    /// 来源文件中最新的修改时间，用作 `Last-Modified`；无来源文件时为 None
+    pub fn last_modified(&self, project: &str, env: &str) -> Result<Option<SystemTime>> {
+        Ok(self
+            .source_files(project, env)?
+            .iter()
+            .filter_map(|f| f.modified)
+            .max())
+    }
+
+    /// 合并配置，`${VAR}` 从给定的变量表取值而非进程环境（不使用缓存）
+    pub fn get_merged_config_with_vars(
         &self,
         project: &str,
         env: &str,
-    ) -> Result<HashMap<String, serde_json::Value>> {
+        vars: &HashMap<String, String>,
+    ) -> Result<MergedConfig> {
+        let source = VarSource {
+            vars: Some(vars),
+            secrets_dir: self.secrets_dir.as_deref(),
+            ..Default::default()
+        };
+        self.compute_merged_config(project, env, &source)
+    }
+
+    /// 导出项目所有环境的合并配置为 NDJSON：每行一个 `{"env": ..., "config": {...}}`，
+    /// 按环境名排序；没有环境时返回空字符串
+    pub fn export_project_ndjson(&self, project: &str) -> Result<String> {
+        let mut out = String::new();
+        for env in self.list_environments(project)? {
+            let config: std::collections::BTreeMap<_, _> = self
+                .merged(project, env)?
+                .as_ref()
+                .clone()
+                .into_iter()
+                .collect();
+            let line = serde_json::json!({ "env": env, "config": config });
+            out.push_str(&line.to_string());
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// 比较同一项目两个环境的合并结果
+    pub fn diff_envs(&self, project: &str, env_a: &str, env_b: &str) -> Result<EnvDiff> {
+        let a = self.merged(project, env_a)?;
+        let b = self.merged(project, env_b)?;
+        let mut diff = EnvDiff::default();
+        for (key, value_a) in a.iter() {
+            match b.get(key) {
+                None => {
+                    diff.removed.insert(key.clone(), value_a.clone());
+                }
+                Some(value_b) if value_b != value_a => {
+                    let change = ValueChange {
+                        from: value_a.clone(),
+                        to: value_b.clone(),
+                    };
+                    diff.changed.insert(key.clone(), change);
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, value_b) in b.iter() {
+            if !a.contains_key(key) {
+                diff.added.insert(key.clone(), value_b.clone());
+            }
+        }
+        Ok(diff)
+    }
+
+    /// 带缓存的合并结果
+    fn merged(&self, project: &str, env: &str) -> Result<Arc<MergedConfig>> {
+        let cache_key = (project.to_string(), env.to_string());
+        if let Some(cached) = self.merged_cache.lock().unwrap().get(&cache_key) {
+            return Ok(cached.clone());
+        }
+
+        let source = VarSource {
+            secrets_dir: self.secrets_dir.as_deref(),
+            ..Default::default()
+        };
+        let merged = Arc::new(self.compute_merged_config(project, env, &source)?);
+        if !source.read_files.get() {
+            self.merged_cache
+                .lock()
+                .unwrap()
+                .insert(cache_key, merged.clone());
+        }
+        Ok(merged)
+    }
+
+    fn compute_merged_config(
+        &self,
+        project: &str,
+        env: &str,
+        source: &VarSource,
+    ) -> Result<MergedConfig> {
+        let mut merged = HashMap::new();
+        for (_, layer) in self.merge_layers(project, env)? {
+            deep_merge(&mut merged, layer);
+        }
+
+        // 解析环境变量替换（key 和值）
+        Ok(resolve_object(merged, source)?.into_iter().collect())
+    }
+
+    /// 按优先级从低到高排列的配置层，附带来源标签（如 `shared/prod`、`my-app/default`）
+    fn merge_layers(&self, project: &str, env: &str) -> Result<Vec<(String, &MergedConfig)>> {
         let state = self.storage.state();
         let proj = state
             .projects
             .get(project)
             .ok_or_else(|| ConfigError::ProjectNotFound(project.to_string()))?;
 
-        let proj_env = proj
-            .environments
-            .get(env)
-            .ok_or_else(|| ConfigError::EnvironmentNotFound(env.to_string()))?;
+        if !proj.environments.contains_key(env) {
+            return Err(ConfigError::EnvironmentNotFound(env.to_string()));
+        }
 
-        let mut merged = HashMap::new();
+        let mut layers = Vec::new();
 
-        // 1. shared/default.yaml（最低优先级）
-        if let Some(shared_default) = state.shared.get("default") {
-            merged.extend(shared_default.clone());
-        }
+        // 1. shared/default.yaml、shared/{env}.yaml（最低优先级）
+        push_env_layers(&mut layers, "shared", &state.shared, env);
 
-        // 2. shared/{env}.yaml 覆盖 shared/default
-        if env != "default" {
-            if let Some(shared_env) = state.shared.get(env) {
-                deep_merge(&mut merged, shared_env);
+        // 2. 项目引用的共享组，按声明顺序依次覆盖
+        for group in &proj.meta.shared_groups {
+            if let Some(group_envs) = state.shared_groups.get(group) {
+                push_env_layers(&mut layers, &format!("shared/{}", group), group_envs, env);
             }
         }
 
-        // 3. 项目 default.yaml
-        if let Some(proj_default) = proj.environments.get("default") {
-            deep_merge(&mut merged, proj_default);
+        // 3. 项目 default.yaml、{env}.yaml（最高优先级）
+        push_env_layers(&mut layers, project, &proj.environments, env);
+
+        Ok(layers)
+    }
+
+    /// 合并配置，并报告覆盖时 JSON 类型发生变化的 key（不影响合并结果）
+    pub fn get_merged_config_with_warnings(
+        &self,
+        project: &str,
+        env: &str,
+    ) -> Result<(MergedConfig, Vec<String>)> {
+        let merged = self.get_merged_config(project, env)?;
+        Ok((merged, self.layer_type_conflicts(project, env)?))
+    }
+
+    /// 运行全部检查并汇总问题，不在第一个问题处停止；项目或环境不存在时返回错误
+    pub fn validate_env(&self, project: &str, env: &str) -> Result<Vec<ValidationIssue>> {
+        let layers = self.merge_layers(project, env)?;
+        let mut issues = Vec::new();
+        let mut push = |kind, message: String| issues.push(ValidationIssue { kind, message });
+
+        let mut unresolved = std::collections::BTreeSet::new();
+        for (label, layer) in &layers {
+            for (key, value) in layer.iter() {
+                collect_unresolved(key, label, key, &mut unresolved);
+                collect_unresolved_in_value(key, label, value, &mut unresolved);
+            }
+        }
+        for message in unresolved {
+            push(IssueKind::UnresolvedVar, message);
         }
 
-        // 4. 项目 {env}.yaml（最高优先级）
-        if env != "default" {
-            deep_merge(&mut merged, proj_env);
+        for message in self.layer_type_conflicts(project, env)? {
+            push(IssueKind::TypeConflict, message);
         }
 
-        // 解析环境变量替换
-        let resolved: HashMap<String, serde_json::Value> = merged
-            .into_iter()
-            .map(|(k, v)| (k, resolve_env_vars(v)))
-            .collect();
+        match self.merged(project, env) {
+            Ok(merged) => {
+                let mut by_env_key: std::collections::BTreeMap<String, Vec<&str>> =
+                    Default::default();
+                for key in merged.keys() {
+                    by_env_key
+                        .entry(to_env_key(key, None))
+                        .or_default()
+                        .push(key);
+                    if let Err(e) = crate::storage::validate_key(key) {
+                        push(IssueKind::InvalidKey, e.to_string());
+                    }
+                }
+                for (env_key, mut keys) in by_env_key {
+                    if keys.len() > 1 {
+                        keys.sort();
+                        push(
+                            IssueKind::EnvKeyCollision,
+                            format!("{} 都会导出为 {}", keys.join(", "), env_key),
+                        );
+                    }
+                }
+            }
+            Err(e) => push(IssueKind::MergeError, e.to_string()),
+        }
+        Ok(issues)
+    }
 
-        Ok(resolved)
+    /// 逐层比较覆盖值与被覆盖值的类型
+    fn layer_type_conflicts(&self, project: &str, env: &str) -> Result<Vec<String>> {
+        let mut warnings = Vec::new();
+        let mut base: MergedConfig = HashMap::new();
+        let mut sources: HashMap<String, String> = HashMap::new();
+        for (label, layer) in self.merge_layers(project, env)? {
+            for (key, value) in layer {
+                if let Some(previous) = base.get(key) {
+                    type_conflicts(key, previous, value, &sources[key], &label, &mut warnings);
+                }
+                sources.insert(key.clone(), label.clone());
+            }
+            deep_merge(&mut base, layer);
+        }
+        warnings.sort();
+        Ok(warnings)
     }
 
     pub fn get_merged_config_item(
@@ -85,7 +654,7 @@ impl ConfigCenter {
         env: &str,
         key: &str,
     ) -> Result<serde_json::Value> {
-        let merged = self.get_merged_config(project, env)?;
+        let merged = self.merged(project, env)?;
         merged
             .get(key)
             .cloned()
@@ -94,11 +663,17 @@ impl ConfigCenter {
 
     /// 验证 API Key，返回 (项目名, key)
     pub fn validate_api_key(&self, key: &str) -> Result<(&str, &str)> {
+        self.find_api_key(key)
+            .map(|(project, entry)| (project, entry.key.as_str()))
+    }
+
+    /// 查找 API Key 条目，返回 (项目名, 条目)
+    pub fn find_api_key(&self, key: &str) -> Result<(&str, &ApiKeyEntry)> {
         let state = self.storage.state();
         for (project_name, project_data) in &state.projects {
             for api_key in &project_data.meta.api_keys {
                 if api_key.key == key {
-                    return Ok((project_name.as_str(), api_key.key.as_str()));
+                    return Ok((project_name.as_str(), api_key));
                 }
             }
         }
@@ -110,149 +685,679 @@ impl ConfigCenter {
         &self,
         project: &str,
         env: &str,
-        prefix: Option<&str>,
+        options: &ExportOptions,
     ) -> Result<HashMap<String, serde_json::Value>> {
         let merged = self.get_merged_config(project, env)?;
-        let mut vars = HashMap::new();
-
-        for (key, value) in merged {
-            let env_key = to_env_key(&key, prefix);
-            vars.insert(env_key, value);
+        let mut vars = to_env_vars(merged, options);
+        for value in vars.values_mut() {
+            match value {
+                serde_json::Value::Bool(b) if options.bool_format == BoolFormat::Numeric => {
+                    *value = serde_json::json!(u8::from(*b));
+                }
+                // 与 get_env_export 一致：标量数组按 csv 连接为字符串
+                serde_json::Value::Array(items)
+                    if options.array_format == ArrayFormat::Csv
+                        && items.iter().all(|v| !v.is_array() && !v.is_object()) =>
+                {
+                    *value = serde_json::Value::String(json_to_env_value(value, options));
+                }
+                _ => {}
+            }
         }
-
         Ok(vars)
     }
 
+    /// 合并配置并渲染其中的模板值
+    pub fn get_rendered_config(&self, project: &str, env: &str) -> Result<MergedConfig> {
+        render_templates(self.merged(project, env)?.as_ref())
+    }
+
     /// 生成 export 格式的字符串
-    pub fn get_env_export(&self, project: &str, env: &str, prefix: Option<&str>) -> Result<String> {
-        let vars = self.get_env_vars(project, env, prefix)?;
-        let mut lines: Vec<String> = vars
+    pub fn get_env_export(
+        &self,
+        project: &str,
+        env: &str,
+        options: &ExportOptions,
+    ) -> Result<String> {
+        let merged = if options.render {
+            self.get_rendered_config(project, env)?
+        } else {
+            self.get_merged_config(project, env)?
+        };
+        let order = &self.project_meta(project)?.order;
+        let vars = to_env_vars(merged, options);
+        let lines = order_env_vars(vars, order, options.prefix.as_deref())
             .iter()
-            .map(|(k, v)| {
-                let s = json_to_env_value(v);
-                if needs_quoting(&s) {
-                    format!(
-                        "export {}=\"{}\"",
-                        k,
-                        s.replace('\\', "\\\\").replace('"', "\\\"")
-                    )
-                } else {
-                    format!("export {}={}", k, s)
-                }
-            })
-            .collect();
-        lines.sort();
+            .map(|(k, v)| format_env_line(options.format, k, &json_to_env_value(v, options)))
+            .collect::<Result<Vec<String>>>()?;
         Ok(lines.join("\n"))
     }
 }
 
-/// 深合并：同名 key 且双方都是 Object 时递归合并子字段，否则 over 覆盖 base
-fn deep_merge(
-    base: &mut HashMap<String, serde_json::Value>,
-    over: &HashMap<String, serde_json::Value>,
+/// 渲染字符串值中的 Tera 模板（含 `{{`、`{%` 或 `{#`），上下文为合并后的配置本身。
+/// 只渲染一轮：模板引用的是未渲染的原值
+pub fn render_templates(config: &MergedConfig) -> Result<MergedConfig> {
+    let context = tera::Context::from_serialize(config)
+        .map_err(|e| ConfigError::Template(template_error_message(&e)))?;
+    config
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), render_value(v, &context)?)))
+        .collect()
+}
+
+fn render_value(value: &serde_json::Value, context: &tera::Context) -> Result<serde_json::Value> {
+    Ok(match value {
+        serde_json::Value::String(s) if ["{{", "{%", "{#"].iter().any(|t| s.contains(t)) => {
+            let rendered = tera::Tera::one_off(s, context, false)
+                .map_err(|e| ConfigError::Template(template_error_message(&e)))?;
+            serde_json::Value::String(rendered)
+        }
+        serde_json::Value::Array(arr) => serde_json::Value::Array(
+            arr.iter()
+                .map(|v| render_value(v, context))
+                .collect::<Result<_>>()?,
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), render_value(v, context)?)))
+                .collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Tera 的顶层错误只有 "Failed to render"，具体原因在 source 链中
+fn template_error_message(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// 配置 key 转为环境变量名；按 key 过滤条件筛选，`NullFormat::Omit` 时丢弃值为 null 的 key
+fn to_env_vars(
+    merged: MergedConfig,
+    options: &ExportOptions,
+) -> HashMap<String, serde_json::Value> {
+    merged
+        .into_iter()
+        .filter(|(key, _)| is_exported(key, options))
+        .filter(|(_, value)| !(value.is_null() && options.null_as == NullFormat::Omit))
+        .map(|(key, value)| (to_env_key(&key, options.prefix.as_deref()), value))
+        .collect()
+}
+
+/// 原始 key 是否通过导出过滤：须匹配 `key_prefix`，且不在排除列表中
+fn is_exported(key: &str, options: &ExportOptions) -> bool {
+    let included = options
+        .key_prefix
+        .as_deref()
+        .is_none_or(|prefix| key.starts_with(prefix));
+    let excluded = options.exclude.iter().any(|k| k == key)
+        || options
+            .exclude_prefix
+            .as_deref()
+            .is_some_and(|prefix| key.starts_with(prefix));
+    included && !excluded
+}
+
+/// 配置的 SHA-256：先规范化为紧凑 JSON，不依赖 serde_json 是否启用 `preserve_order`
+fn checksum(config: &MergedConfig) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut canonical = String::new();
+    write_canonical_object(config.iter(), &mut canonical);
+    format!("{:x}", Sha256::digest(canonical.as_bytes()))
+}
+
+/// 按 key 排序写出对象
+fn write_canonical_object<'a>(
+    entries: impl Iterator<Item = (&'a String, &'a serde_json::Value)>,
+    out: &mut String,
 ) {
-    for (k, v) in over {
-        match (base.get(k), v) {
-            (Some(serde_json::Value::Object(b)), serde_json::Value::Object(o)) => {
-                let mut m = b.clone();
-                for (ik, iv) in o {
-                    m.insert(ik.clone(), iv.clone());
+    let mut entries: Vec<_> = entries.collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    out.push('{');
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&serde_json::Value::String(key.clone()).to_string());
+        out.push(':');
+        write_canonical(value, out);
+    }
+    out.push('}');
+}
+
+/// 递归写出规范化 JSON：对象按 key 排序，其余值按 serde_json 的紧凑格式
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => write_canonical_object(map.iter(), out),
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
                 }
-                base.insert(k.clone(), serde_json::Value::Object(m));
-            }
-            _ => {
-                base.insert(k.clone(), v.clone());
+                write_canonical(item, out);
             }
+            out.push(']');
         }
+        other => out.push_str(&other.to_string()),
     }
 }
 
-/// key 转环境变量名：大写，点和横线转下划线，加可选前缀
-fn to_env_key(key: &str, prefix: Option<&str>) -> String {
-    let normalized = key.replace('.', "_").replace('-', "_").to_uppercase();
-    match prefix {
-        Some(p) => format!("{}_{}", p.to_uppercase(), normalized),
-        None => normalized,
+/// 按 `order`（配置 key 名）排列环境变量，未列出的按变量名排序后追加
+fn order_env_vars(
+    vars: HashMap<String, serde_json::Value>,
+    order: &[String],
+    prefix: Option<&str>,
+) -> Vec<(String, serde_json::Value)> {
+    let ranks: HashMap<String, usize> = order
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, key)| (to_env_key(key, prefix), i))
+        .collect();
+    let mut sorted: Vec<_> = vars.into_iter().collect();
+    sorted.sort_by(|(a, _), (b, _)| {
+        let rank = |k: &String| ranks.get(k).copied().unwrap_or(usize::MAX);
+        rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+    });
+    sorted
+}
+
+/// 追加同一来源的配置层：default 在前，{env} 覆盖
+fn push_env_layers<'a>(
+    layers: &mut Vec<(String, &'a MergedConfig)>,
+    label: &str,
+    envs: &'a HashMap<String, MergedConfig>,
+    env: &str,
+) {
+    if let Some(default) = envs.get("default") {
+        layers.push((format!("{}/default", label), default));
+    }
+    if env != "default" {
+        if let Some(env_config) = envs.get(env) {
+            layers.push((format!("{}/{}", label, env), env_config));
+        }
     }
 }
 
-/// JSON 值转环境变量值
-fn json_to_env_value(value: &serde_json::Value) -> String {
-    match value {
-        serde_json::Value::String(s) => s.clone(),
-        serde_json::Value::Number(n) => n.to_string(),
-        serde_json::Value::Bool(b) => b.to_string(),
-        serde_json::Value::Null => String::new(),
-        // 复杂类型序列化为 JSON 字符串
-        other => serde_json::to_string(other).unwrap_or_default(),
+/// 收集字符串中引用了未设置环境变量的 `${VAR}`（`${file:...}` 在合并时单独报错）
+fn collect_unresolved(
+    key: &str,
+    label: &str,
+    s: &str,
+    unresolved: &mut std::collections::BTreeSet<String>,
+) {
+    for name in var_refs(s) {
+        if !name.starts_with("file:") && std::env::var(name).is_err() {
+            unresolved.insert(format!("{} ({}): ${{{}}} 未设置", key, label, name));
+        }
     }
 }
 
-/// 判断值是否需要引号（包含空格或特殊字符）
-fn needs_quoting(value: &str) -> bool {
-    value.is_empty()
-        || value.contains(' ')
-        || value.contains('"')
-        || value.contains('\'')
-        || value.contains('$')
-        || value.contains('`')
-        || value.contains('\\')
-        || value.contains('\n')
-        || value.contains('{')
-        || value.contains('}')
-        || value.contains('[')
-        || value.contains(']')
-}
-/// Recursively resolve ${VAR} patterns in JSON values using process environment variables.
-/// - "${VAR}" as the entire string → replaced with env var value (string)
-/// - "prefix_${VAR}_suffix" → string interpolation
-/// - If env var is not set, keep the original "${VAR}" unchanged
-fn resolve_env_vars(value: serde_json::Value) -> serde_json::Value {
+fn collect_unresolved_in_value(
+    key: &str,
+    label: &str,
+    value: &serde_json::Value,
+    unresolved: &mut std::collections::BTreeSet<String>,
+) {
     match value {
-        serde_json::Value::String(s) => serde_json::Value::String(substitute_env_in_string(&s)),
-        serde_json::Value::Array(arr) => {
-            serde_json::Value::Array(arr.into_iter().map(resolve_env_vars).collect())
+        serde_json::Value::String(s) => collect_unresolved(key, label, s, unresolved),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_unresolved_in_value(key, label, item, unresolved);
+            }
         }
-        serde_json::Value::Object(map) => serde_json::Value::Object(
-            map.into_iter()
-                .map(|(k, v)| (k, resolve_env_vars(v)))
-                .collect(),
-        ),
-        other => other, // numbers, bools, null unchanged
+        serde_json::Value::Object(map) => {
+            for (inner, item) in map {
+                let path = format!("{}.{}", key, inner);
+                collect_unresolved(&path, label, inner, unresolved);
+                collect_unresolved_in_value(&path, label, item, unresolved);
+            }
+        }
+        _ => {}
     }
 }
 
-/// Replace ${VAR} patterns in a string with environment variable values.
-fn substitute_env_in_string(s: &str) -> String {
-    let mut result = s.to_string();
-    let mut search_from = 0;
-    while let Some(rel_start) = result[search_from..].find("${") {
-        let start = search_from + rel_start;
-        if let Some(rel_end) = result[start..].find('}') {
-            let end = start + rel_end;
-            let var_name = &result[start + 2..end];
-            match std::env::var(var_name) {
-                Ok(val) => {
-                    result = format!("{}{}{}", &result[..start], val, &result[end + 1..]);
-                    search_from = start + val.len();
-                }
-                Err(_) => {
-                    // 环境变量不存在，跳过这个 ${...}，继续往后搜
-                    search_from = end + 1;
-                }
-            }
+/// 字符串中的 `${NAME}` 引用，解析规则与 `substitute_env_in_string` 一致
+fn var_refs(s: &str) -> Vec<&str> {
+    let mut refs = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let name_len = after.find(['$', '{', '}']).unwrap_or(after.len());
+        if name_len > 0 && after[name_len..].starts_with('}') {
+            refs.push(&after[..name_len]);
+            rest = &after[name_len + 1..];
         } else {
-            break;
+            rest = after;
         }
     }
-    result
+    refs
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// JSON 类型名
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "bool",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// 检查覆盖值与原值的类型是否一致；双方都是对象时与 deep_merge 一样逐个比较子字段
+fn type_conflicts(
+    key: &str,
+    base: &serde_json::Value,
+    over: &serde_json::Value,
+    base_source: &str,
+    over_source: &str,
+    warnings: &mut Vec<String>,
+) {
+    match (base, over) {
+        (serde_json::Value::Object(b), serde_json::Value::Object(o)) => {
+            for (inner, value) in o {
+                if let Some(previous) = b.get(inner) {
+                    if json_type(previous) != json_type(value) {
+                        warnings.push(format!(
+                            "{}.{}: {} ({}) 被 {} ({}) 覆盖",
+                            key,
+                            inner,
+                            json_type(previous),
+                            base_source,
+                            json_type(value),
+                            over_source
+                        ));
+                    }
+                }
+            }
+        }
+        _ if json_type(base) != json_type(over) => {
+            warnings.push(format!(
+                "{}: {} ({}) 被 {} ({}) 覆盖",
+                key,
+                json_type(base),
+                base_source,
+                json_type(over),
+                over_source
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// 深合并：同名 key 且双方都是 Object 时递归合并子字段，否则 over 覆盖 base
+fn deep_merge(
+    base: &mut HashMap<String, serde_json::Value>,
+    over: &HashMap<String, serde_json::Value>,
+) {
+    for (k, v) in over {
+        match (base.get(k), v) {
+            (Some(serde_json::Value::Object(b)), serde_json::Value::Object(o)) => {
+                let mut m = b.clone();
+                for (ik, iv) in o {
+                    m.insert(ik.clone(), iv.clone());
+                }
+                base.insert(k.clone(), serde_json::Value::Object(m));
+            }
+            _ => {
+                base.insert(k.clone(), v.clone());
+            }
+        }
+    }
+}
+
+/// 展开为扁平的点分 key：嵌套对象 `db.host`，数组元素 `hosts.0`；
+/// 空对象/空数组作为叶子值保留
+pub fn flatten_config(config: &MergedConfig) -> MergedConfig {
+    let mut flat = HashMap::new();
+    for (k, v) in config {
+        flatten_value(k, v, &mut flat);
+    }
+    flat
+}
+
+fn flatten_value(path: &str, value: &serde_json::Value, flat: &mut MergedConfig) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                flatten_value(&format!("{}.{}", path, k), v, flat);
+            }
+        }
+        serde_json::Value::Array(arr) if !arr.is_empty() => {
+            for (i, v) in arr.iter().enumerate() {
+                flatten_value(&format!("{}.{}", path, i), v, flat);
+            }
+        }
+        other => {
+            flat.insert(path.to_string(), other.clone());
+        }
+    }
+}
+
+/// flatten_config 的逆操作：点分 key 还原为嵌套对象，
+/// 子 key 恰为 0..n 的对象还原为数组。
+/// 同一路径既是标量又是对象（如 `db` 与 `db.host`）时返回 KeyConflict
+pub fn unflatten_config(flat: &MergedConfig) -> Result<MergedConfig> {
+    let mut keys: Vec<&String> = flat.keys().collect();
+    keys.sort();
+
+    let mut root = serde_json::Map::new();
+    for key in keys {
+        let segments: Vec<&str> = key.split('.').collect();
+        let (last, parents) = segments.split_last().unwrap();
+        let mut node = &mut root;
+        for (i, segment) in parents.iter().enumerate() {
+            let child = node
+                .entry(segment.to_string())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            node = child.as_object_mut().ok_or_else(|| {
+                ConfigError::KeyConflict(format!(
+                    "{} is not an object (required by {})",
+                    segments[..=i].join("."),
+                    key
+                ))
+            })?;
+        }
+        if node.contains_key(*last) {
+            return Err(ConfigError::KeyConflict(format!(
+                "{} is both a value and an object",
+                key
+            )));
+        }
+        node.insert(last.to_string(), flat[key].clone());
+    }
+
+    Ok(root
+        .into_iter()
+        .map(|(k, v)| (k, restore_arrays(v)))
+        .collect())
+}
+
+fn restore_arrays(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(mut map) => {
+            let is_array =
+                !map.is_empty() && (0..map.len()).all(|i| map.contains_key(&i.to_string()));
+            if is_array {
+                let items = (0..map.len())
+                    .map(|i| restore_arrays(map.remove(&i.to_string()).unwrap()))
+                    .collect();
+                serde_json::Value::Array(items)
+            } else {
+                serde_json::Value::Object(
+                    map.into_iter()
+                        .map(|(k, v)| (k, restore_arrays(v)))
+                        .collect(),
+                )
+            }
+        }
+        other => other,
+    }
+}
+
+/// key 转环境变量名：大写，点和横线转下划线，加可选前缀
+fn to_env_key(key: &str, prefix: Option<&str>) -> String {
+    let normalized = key.replace(['.', '-'], "_").to_uppercase();
+    match prefix {
+        Some(p) => format!("{}_{}", p.to_uppercase(), normalized),
+        None => normalized,
+    }
+}
+
+/// JSON 值转环境变量值
+fn json_to_env_value(value: &serde_json::Value, options: &ExportOptions) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::Bool(b) => match options.bool_format {
+            BoolFormat::Literal => b.to_string(),
+            BoolFormat::Numeric => u8::from(*b).to_string(),
+        },
+        serde_json::Value::Null => match options.null_as {
+            NullFormat::Literal => "null".to_string(),
+            NullFormat::Empty | NullFormat::Omit => String::new(),
+        },
+        serde_json::Value::Array(items)
+            if options.array_format == ArrayFormat::Csv
+                && items.iter().all(|v| !v.is_array() && !v.is_object()) =>
+        {
+            let separator = options.separator.as_deref().unwrap_or(",");
+            items
+                .iter()
+                .map(|v| json_to_env_value(v, options))
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+        // 复杂类型序列化为 JSON 字符串
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+/// 按目标 shell 的语法生成一行赋值
+fn format_env_line(format: ExportFormat, key: &str, value: &str) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Shell if needs_quoting(value) => {
+            format!("export {}=\"{}\"", key, escape_double_quoted(value))
+        }
+        ExportFormat::Shell => format!("export {}={}", key, value),
+        ExportFormat::Powershell => format!("$env:{} = \"{}\"", key, escape_powershell(value)),
+        ExportFormat::Cmd => format!("set \"{}={}\"", key, escape_cmd(key, value)?),
+        ExportFormat::Fish if needs_quoting(value) => {
+            format!("set -gx {} '{}'", key, escape_fish_single_quoted(value))
+        }
+        ExportFormat::Fish => format!("set -gx {} {}", key, value),
+    })
+}
+
+/// PowerShell 双引号内用反引号转义：`` ` ``、`"`、`$`
+fn escape_powershell(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '`' | '"' | '$') {
+            escaped.push('`');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// fish 单引号内只有 `\` 和 `'` 需要转义
+fn escape_fish_single_quoted(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// 转义 `set "KEY=value"` 中的值。`%` 在引号内也会展开，写作 `%%`；
+/// 值中的 `"` 会切换引号状态，引号外的元字符 `^ & | < > ( )` 用 `^` 转义。
+/// cmd 无法在一行内表示换行，含 `\r`/`\n` 的值无法安全导出，返回错误
+fn escape_cmd(key: &str, value: &str) -> Result<String> {
+    if value.contains(['\r', '\n']) {
+        return Err(ConfigError::InvalidParameter(format!(
+            "{} contains a line break and cannot be exported in cmd format",
+            key
+        )));
+    }
+    let mut escaped = String::with_capacity(value.len());
+    let mut quoted = true;
+    for c in value.chars() {
+        match c {
+            '%' => escaped.push('%'),
+            '"' => quoted = !quoted,
+            '^' | '&' | '|' | '<' | '>' | '(' | ')' if !quoted => escaped.push('^'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    Ok(escaped)
+}
+
+/// 判断值是否需要引号：除明确安全的字符外（含空白、制表符、回车、`#`、`;` 等 shell 元字符）一律加引号
+fn needs_quoting(value: &str) -> bool {
+    value.is_empty()
+        || !value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@%+=".contains(c))
+}
+
+/// 双引号内转义：`\`、`"`、`$`、`` ` `` 在双引号中仍有特殊含义
+fn escape_double_quoted(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Where ${VAR} values come from: the process environment by default, or an injected map.
+/// `${file:path}` reads a file inside `secrets_dir` (disabled when unset).
+#[derive(Default)]
+struct VarSource<'a> {
+    vars: Option<&'a HashMap<String, String>>,
+    secrets_dir: Option<&'a Path>,
+    /// Set once a file reference has been read; such results must not be cached.
+    read_files: std::cell::Cell<bool>,
+}
+
+impl VarSource<'_> {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        if let Some(path) = name.strip_prefix("file:") {
+            return self.read_file(path).map(Some);
+        }
+        Ok(match self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        })
+    }
+
+    /// Read a secret file (trimmed). Relative paths are resolved against `secrets_dir`;
+    /// the canonical path must stay inside it.
+    fn read_file(&self, path: &str) -> Result<String> {
+        self.read_files.set(true);
+        let dir = self.secrets_dir.ok_or_else(|| {
+            ConfigError::FileReference(format!("file references are disabled: {}", path))
+        })?;
+        let not_found = |e: std::io::Error| ConfigError::FileReference(format!("{}: {}", path, e));
+        let dir = dir.canonicalize().map_err(not_found)?;
+        let file = dir.join(path).canonicalize().map_err(not_found)?;
+        if !file.starts_with(&dir) {
+            return Err(ConfigError::FileReference(format!(
+                "{} is outside the allowed directory",
+                path
+            )));
+        }
+        let content = std::fs::read_to_string(&file).map_err(not_found)?;
+        Ok(content.trim().to_string())
+    }
+}
+
+/// Recursively resolve ${VAR} patterns in JSON values using the given variable source.
+/// - "${VAR}" as the entire string → replaced with env var value (string)
+/// - "prefix_${VAR}_suffix" → string interpolation
+/// - If env var is not set, keep the original "${VAR}" unchanged
+/// - Object keys are resolved the same way (see `resolve_object`)
+fn resolve_env_vars(value: serde_json::Value, source: &VarSource) -> Result<serde_json::Value> {
+    Ok(match value {
+        serde_json::Value::String(s) => serde_json::Value::String(resolve_string(&s, source)?),
+        serde_json::Value::Array(arr) => serde_json::Value::Array(
+            arr.into_iter()
+                .map(|v| resolve_env_vars(v, source))
+                .collect::<Result<_>>()?,
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(resolve_object(map, source)?),
+        other => other, // numbers, bools, null unchanged
+    })
+}
+
+/// Resolve a string value. A whole value of `@file:path` is replaced by the file content
+/// (same rules as `${file:path}`); `@@file:` escapes it to a literal `@file:`.
+fn resolve_string(s: &str, source: &VarSource) -> Result<String> {
+    if let Some(path) = s.strip_prefix("@file:") {
+        return source.read_file(path);
+    }
+    if let Some(literal) = s.strip_prefix("@@file:") {
+        return Ok(format!("@file:{}", literal));
+    }
+    substitute_env_in_string(s, source)
+}
+
+/// Resolve ${VAR} in both object keys and values.
+/// If two keys resolve to the same string, entries are applied in original key order
+/// and the last one wins (a warning is logged).
+fn resolve_object(
+    entries: impl IntoIterator<Item = (String, serde_json::Value)>,
+    source: &VarSource,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let mut sorted: Vec<(String, serde_json::Value)> = entries.into_iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut resolved = serde_json::Map::new();
+    for (key, value) in sorted {
+        let new_key = substitute_env_in_string(&key, source)?;
+        if resolved
+            .insert(new_key.clone(), resolve_env_vars(value, source)?)
+            .is_some()
+        {
+            tracing::warn!("环境变量替换后 key 重复: {}（由 {} 覆盖）", new_key, key);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Replace ${VAR} patterns in a string with values from the variable source.
+///
+/// Matching rules (single left-to-right pass):
+/// - A reference is `${NAME}` where NAME is non-empty and contains no `$`, `{` or `}`.
+/// - A `${` that does not start a reference is kept literally and scanning resumes
+///   right after it, so in `${A${B}}` only the innermost `${B}` is substituted.
+/// - Unclosed `${`, empty `${}` and unset variables are left unchanged.
+/// - Substituted values are never rescanned.
+/// - `${file:path}` is replaced by the file content; a missing or disallowed file is an error.
+fn substitute_env_in_string(s: &str, source: &VarSource) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name_len = after.find(['$', '{', '}']).unwrap_or(after.len());
+        let name = &after[..name_len];
+        let closed = after[name_len..].starts_with('}');
+        if closed && !name.is_empty() {
+            if let Some(val) = source.get(name)? {
+                result.push_str(&val);
+                rest = &after[name_len + 1..];
+                continue;
+            }
+        }
+        result.push_str("${");
+        rest = after;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     use tempfile::TempDir;
 
+    /// 辅助：从进程环境变量替换
+    fn substitute(s: &str) -> String {
+        substitute_env_in_string(s, &VarSource::default()).unwrap()
+    }
+
     /// 辅助：创建临时配置目录结构
     fn setup_config_dir(tmp: &TempDir) {
         let base = tmp.path();
@@ -292,6 +1397,21 @@ mod tests {
         assert!(projects.contains(&"my-app"));
     }
 
+    #[test]
+    fn test_list_projects_sorted() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        for name in ["zeta", "alpha", "Mixed", "beta-2", "beta"] {
+            std::fs::create_dir_all(tmp.path().join("projects").join(name)).unwrap();
+        }
+
+        let expected = vec!["Mixed", "alpha", "beta", "beta-2", "my-app", "zeta"];
+        for _ in 0..5 {
+            let center = ConfigCenter::new(tmp.path()).unwrap();
+            assert_eq!(center.list_projects(), expected);
+        }
+    }
+
     #[test]
     fn test_merged_config_project_overrides_shared() {
         let tmp = TempDir::new().unwrap();
@@ -386,13 +1506,37 @@ mod tests {
         assert!(matches!(err, ConfigError::Unauthorized(_)));
     }
 
+    #[test]
+    fn test_admin_key_survives_reload() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+
+        let center = ConfigCenter::new(tmp.path())
+            .unwrap()
+            .with_admin_key(Some("admin-secret".to_string()));
+        assert!(center.is_admin_key("admin-secret"));
+        assert!(!center.is_admin_key("test-key-123"));
+        // 管理员 Key 不是项目 Key
+        assert!(center.validate_api_key("admin-secret").is_err());
+
+        let next = center.reloaded().unwrap();
+        assert!(next.is_admin_key("admin-secret"));
+
+        let unset = ConfigCenter::new(tmp.path())
+            .unwrap()
+            .with_admin_key(Some(String::new()));
+        assert!(!unset.is_admin_key(""));
+    }
+
     #[test]
     fn test_env_vars_basic() {
         let tmp = TempDir::new().unwrap();
         setup_config_dir(&tmp);
 
         let center = ConfigCenter::new(tmp.path()).unwrap();
-        let vars = center.get_env_vars("my-app", "default", None).unwrap();
+        let vars = center
+            .get_env_vars("my-app", "default", &ExportOptions::default())
+            .unwrap();
 
         assert_eq!(vars["DB_HOST"], serde_json::json!("localhost"));
         assert_eq!(vars["DB_PORT"], serde_json::json!(5432));
@@ -407,13 +1551,263 @@ mod tests {
 
         let center = ConfigCenter::new(tmp.path()).unwrap();
         let vars = center
-            .get_env_vars("my-app", "default", Some("MY_APP"))
+            .get_env_vars(
+                "my-app",
+                "default",
+                &ExportOptions {
+                    prefix: Some("MY_APP".to_string()),
+                    ..Default::default()
+                },
+            )
             .unwrap();
 
         assert_eq!(vars["MY_APP_DB_HOST"], serde_json::json!("localhost"));
         assert_eq!(vars["MY_APP_DB_PORT"], serde_json::json!(5432));
     }
 
+    #[test]
+    fn test_env_vars_key_prefix() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        let options = |key_prefix: &str| ExportOptions {
+            prefix: Some("MY_APP".to_string()),
+            key_prefix: Some(key_prefix.to_string()),
+            ..Default::default()
+        };
+
+        // 匹配原始 key，而不是加了前缀、转成大写后的变量名
+        let vars = center
+            .get_env_vars("my-app", "default", &options("db_"))
+            .unwrap();
+        let mut names: Vec<_> = vars.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, ["MY_APP_DB_HOST", "MY_APP_DB_PORT"]);
+        assert!(center
+            .get_env_vars("my-app", "default", &options("DB_"))
+            .unwrap()
+            .is_empty());
+
+        // 没有匹配时返回空结果而不是错误
+        assert!(center
+            .get_env_vars("my-app", "default", &options("redis_"))
+            .unwrap()
+            .is_empty());
+        let export = center
+            .get_env_export("my-app", "default", &options("redis_"))
+            .unwrap();
+        assert_eq!(export, "");
+    }
+
+    #[test]
+    fn test_env_vars_exclude() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        let names = |options: ExportOptions| {
+            let mut names: Vec<_> = center
+                .get_env_vars("my-app", "default", &options)
+                .unwrap()
+                .into_keys()
+                .collect();
+            names.sort();
+            names
+        };
+
+        // 按名称排除
+        assert_eq!(
+            names(ExportOptions {
+                exclude: vec!["db_port".to_string(), "timeout".to_string()],
+                ..Default::default()
+            }),
+            ["DB_HOST", "LOG_LEVEL"]
+        );
+
+        // 按前缀排除
+        assert_eq!(
+            names(ExportOptions {
+                exclude_prefix: Some("db_".to_string()),
+                ..Default::default()
+            }),
+            ["LOG_LEVEL", "TIMEOUT"]
+        );
+
+        // 与 key_prefix 同时使用时排除优先
+        assert_eq!(
+            names(ExportOptions {
+                key_prefix: Some("db_".to_string()),
+                exclude: vec!["db_port".to_string()],
+                ..Default::default()
+            }),
+            ["DB_HOST"]
+        );
+        assert!(names(ExportOptions {
+            key_prefix: Some("db_".to_string()),
+            exclude_prefix: Some("db".to_string()),
+            ..Default::default()
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn test_env_vars_array_format() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "hosts: [a, b]\nports: [80, 443]\nservers: [{host: a}]\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+
+        let vars = center
+            .get_env_vars("my-app", "default", &ExportOptions::default())
+            .unwrap();
+        assert_eq!(vars["HOSTS"], serde_json::json!(["a", "b"]));
+
+        let csv = ExportOptions {
+            array_format: ArrayFormat::Csv,
+            separator: Some(";".to_string()),
+            ..Default::default()
+        };
+        let vars = center.get_env_vars("my-app", "default", &csv).unwrap();
+        assert_eq!(vars["HOSTS"], serde_json::json!("a;b"));
+        assert_eq!(vars["PORTS"], serde_json::json!("80;443"));
+        // 含对象的数组保持原样
+        assert_eq!(vars["SERVERS"], serde_json::json!([{"host": "a"}]));
+    }
+
+    #[test]
+    fn test_env_bool_formats() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "debug: true\ncache: false\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        let literal = ExportOptions::default();
+        let export = center.get_env_export("app", "default", &literal).unwrap();
+        assert_eq!(export, "export CACHE=false\nexport DEBUG=true");
+        let vars = center.get_env_vars("app", "default", &literal).unwrap();
+        assert_eq!(vars["DEBUG"], serde_json::json!(true));
+
+        let numeric = ExportOptions {
+            bool_format: BoolFormat::Numeric,
+            ..Default::default()
+        };
+        let export = center.get_env_export("app", "default", &numeric).unwrap();
+        assert_eq!(export, "export CACHE=0\nexport DEBUG=1");
+        let vars = center.get_env_vars("app", "default", &numeric).unwrap();
+        assert_eq!(vars["DEBUG"], serde_json::json!(1));
+        assert_eq!(vars["CACHE"], serde_json::json!(0));
+    }
+
+    #[test]
+    fn test_env_null_formats() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "db_host: localhost\nproxy: null\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+        let options = |null_as| ExportOptions {
+            null_as,
+            ..Default::default()
+        };
+
+        let export = center
+            .get_env_export("app", "default", &options(NullFormat::Empty))
+            .unwrap();
+        assert_eq!(export, "export DB_HOST=localhost\nexport PROXY=\"\"");
+
+        let export = center
+            .get_env_export("app", "default", &options(NullFormat::Literal))
+            .unwrap();
+        assert_eq!(export, "export DB_HOST=localhost\nexport PROXY=null");
+
+        let export = center
+            .get_env_export("app", "default", &options(NullFormat::Omit))
+            .unwrap();
+        assert_eq!(export, "export DB_HOST=localhost");
+        let vars = center
+            .get_env_vars("app", "default", &options(NullFormat::Omit))
+            .unwrap();
+        assert!(!vars.contains_key("PROXY"));
+        assert!(vars.contains_key("DB_HOST"));
+    }
+
+    #[test]
+    fn test_flatten_config() {
+        let mut config = HashMap::new();
+        config.insert(
+            "db".to_string(),
+            serde_json::json!({"host": "localhost", "pool": {"max": 10}}),
+        );
+        config.insert("hosts".to_string(), serde_json::json!(["a", {"name": "b"}]));
+        config.insert("empty".to_string(), serde_json::json!({}));
+        config.insert("debug".to_string(), serde_json::json!(false));
+
+        let flat = flatten_config(&config);
+        assert_eq!(flat.len(), 6);
+        assert_eq!(flat["db.host"], "localhost");
+        assert_eq!(flat["db.pool.max"], 10);
+        assert_eq!(flat["hosts.0"], "a");
+        assert_eq!(flat["hosts.1.name"], "b");
+        assert_eq!(flat["empty"], serde_json::json!({}));
+        assert_eq!(flat["debug"], false);
+
+        assert_eq!(unflatten_config(&flat).unwrap(), config);
+    }
+
+    #[test]
+    fn test_unflatten_config() {
+        let mut flat = HashMap::new();
+        flat.insert("db.host".to_string(), serde_json::json!("localhost"));
+        flat.insert("db.port".to_string(), serde_json::json!(5432));
+        flat.insert("name".to_string(), serde_json::json!("app"));
+
+        let nested = unflatten_config(&flat).unwrap();
+        assert_eq!(nested.len(), 2);
+        assert_eq!(
+            nested["db"],
+            serde_json::json!({"host": "localhost", "port": 5432})
+        );
+        assert_eq!(nested["name"], "app");
+
+        // 标量与对象冲突（与插入顺序无关）
+        flat.insert("db".to_string(), serde_json::json!("x"));
+        let err = unflatten_config(&flat).unwrap_err();
+        assert!(matches!(err, ConfigError::KeyConflict(_)));
+        assert!(err.to_string().contains("db"));
+
+        let mut flat = HashMap::new();
+        flat.insert("a.b".to_string(), serde_json::json!(1));
+        flat.insert("a.b.c".to_string(), serde_json::json!(2));
+        assert!(matches!(
+            unflatten_config(&flat),
+            Err(ConfigError::KeyConflict(_))
+        ));
+    }
+
     #[test]
     fn test_env_key_conversion() {
         assert_eq!(to_env_key("db_host", None), "DB_HOST");
@@ -423,41 +1817,1186 @@ mod tests {
     }
 
     #[test]
-    fn test_json_to_env_value_types() {
-        assert_eq!(json_to_env_value(&serde_json::json!("hello")), "hello");
-        assert_eq!(json_to_env_value(&serde_json::json!(42)), "42");
-        assert_eq!(json_to_env_value(&serde_json::json!(true)), "true");
-        assert_eq!(json_to_env_value(&serde_json::json!(null)), "");
-        // 复杂类型序列化为 JSON
-        let arr = json_to_env_value(&serde_json::json!(["a", "b"]));
-        assert_eq!(arr, r#"["a","b"]"#);
+    fn test_json_to_env_value_types() {
+        assert_eq!(
+            json_to_env_value(&serde_json::json!("hello"), &ExportOptions::default()),
+            "hello"
+        );
+        assert_eq!(
+            json_to_env_value(&serde_json::json!(42), &ExportOptions::default()),
+            "42"
+        );
+        assert_eq!(
+            json_to_env_value(&serde_json::json!(true), &ExportOptions::default()),
+            "true"
+        );
+        assert_eq!(
+            json_to_env_value(&serde_json::json!(null), &ExportOptions::default()),
+            ""
+        );
+        // 复杂类型序列化为 JSON
+        let arr = json_to_env_value(&serde_json::json!(["a", "b"]), &ExportOptions::default());
+        assert_eq!(arr, r#"["a","b"]"#);
+    }
+
+    #[test]
+    fn test_json_to_env_value_csv_arrays() {
+        let mut options = ExportOptions {
+            array_format: ArrayFormat::Csv,
+            ..Default::default()
+        };
+        let hosts = serde_json::json!(["a.example.com", "b.example.com"]);
+        assert_eq!(
+            json_to_env_value(&hosts, &options),
+            "a.example.com,b.example.com"
+        );
+        assert_eq!(
+            json_to_env_value(&serde_json::json!([1, true]), &options),
+            "1,true"
+        );
+
+        options.separator = Some(";".to_string());
+        assert_eq!(
+            json_to_env_value(&hosts, &options),
+            "a.example.com;b.example.com"
+        );
+
+        // 含对象的数组仍输出 JSON
+        let objects = serde_json::json!([{"host": "a"}]);
+        assert_eq!(json_to_env_value(&objects, &options), r#"[{"host":"a"}]"#);
+    }
+
+    #[test]
+    fn test_env_export_format() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "db_host: localhost\ndb_port: 5432\n",
+        )
+        .unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        let export = center
+            .get_env_export("app", "default", &ExportOptions::default())
+            .unwrap();
+
+        assert!(export.contains("export DB_HOST=localhost"));
+        assert!(export.contains("export DB_PORT=5432"));
+    }
+
+    #[test]
+    fn test_env_export_quoting() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "greeting: hello world\n",
+        )
+        .unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        let export = center
+            .get_env_export("app", "default", &ExportOptions::default())
+            .unwrap();
+
+        assert!(export.contains("export GREETING=\"hello world\""));
+    }
+
+    #[test]
+    fn test_env_export_order() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "zone: a\nport: 80\nhost: h\ndebug: false\napi.url: u\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+        let export = center
+            .get_env_export("app", "default", &ExportOptions::default())
+            .unwrap();
+        assert_eq!(
+            export,
+            "export API_URL=u\nexport DEBUG=false\nexport HOST=h\nexport PORT=80\nexport ZONE=a"
+        );
+
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "order: [port, host, api.url, missing]\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+        let options = ExportOptions {
+            prefix: Some("app".to_string()),
+            ..Default::default()
+        };
+        let export = center.get_env_export("app", "default", &options).unwrap();
+        let keys: Vec<&str> = export
+            .lines()
+            .map(|l| l.trim_start_matches("export ").split('=').next().unwrap())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "APP_PORT",
+                "APP_HOST",
+                "APP_API_URL",
+                "APP_DEBUG",
+                "APP_ZONE"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_export_windows_formats() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "greeting: 'say \"hi\" & pay $5'\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        let powershell = ExportOptions {
+            format: ExportFormat::Powershell,
+            ..Default::default()
+        };
+        let export = center
+            .get_env_export("app", "default", &powershell)
+            .unwrap();
+        assert_eq!(export, "$env:GREETING = \"say `\"hi`\" & pay `$5\"");
+
+        let cmd = ExportOptions {
+            format: ExportFormat::Cmd,
+            ..Default::default()
+        };
+        let export = center.get_env_export("app", "default", &cmd).unwrap();
+        assert_eq!(export, "set \"GREETING=say \"hi\" & pay $5\"");
+    }
+
+    #[test]
+    fn test_env_export_cmd_escaping() {
+        let line = |value: &str| format_env_line(ExportFormat::Cmd, "A", value);
+
+        assert_eq!(line("a & b").unwrap(), "set \"A=a & b\"");
+        // %VAR% 在引号内也会展开
+        assert_eq!(
+            line("%PATH%;C:\\bin").unwrap(),
+            "set \"A=%%PATH%%;C:\\bin\""
+        );
+        // 不成对的引号之后处于引号外，元字符需要转义
+        assert_eq!(line("x\" & calc").unwrap(), "set \"A=x\" ^& calc\"");
+        assert_eq!(line("a\"(b)\"c|d").unwrap(), "set \"A=a\"^(b^)\"c|d\"");
+        // 换行会让 cmd 把后半段当作新命令执行
+        for value in ["x\ncalc.exe & del /q C:\\*", "x\r\ny"] {
+            assert!(matches!(line(value), Err(ConfigError::InvalidParameter(_))));
+        }
+    }
+
+    #[test]
+    fn test_env_export_fish_format() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "db_host: localhost\ngreeting: it's a \"test\" in C:\\tmp\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        let options = ExportOptions {
+            format: ExportFormat::Fish,
+            ..Default::default()
+        };
+        let export = center.get_env_export("app", "default", &options).unwrap();
+        assert!(export.contains("set -gx DB_HOST localhost"));
+        assert!(export.contains(r#"set -gx GREETING 'it\'s a "test" in C:\\tmp'"#));
+    }
+
+    #[test]
+    fn test_needs_quoting_whitespace_and_metacharacters() {
+        for value in [
+            "\ttabbed",
+            "trailing ",
+            " leading",
+            "line\rcr",
+            "a#b",
+            "#comment",
+            "a;rm -rf",
+            "a&b",
+            "a|b",
+            "(x)",
+            "*",
+            "~",
+            "",
+        ] {
+            assert!(needs_quoting(value), "{:?}", value);
+        }
+        for value in [
+            "plain",
+            "db.example.com:5432",
+            "https://x.io/a",
+            "a=b",
+            "50%",
+            "x@y",
+        ] {
+            assert!(!needs_quoting(value), "{:?}", value);
+        }
+
+        // 双引号内 $ 和 ` 也需转义，避免展开
+        assert_eq!(
+            escape_double_quoted("a \"b\" $HOME `id` \\"),
+            "a \\\"b\\\" \\$HOME \\`id\\` \\\\"
+        );
+    }
+
+    #[test]
+    fn test_shared_groups_layered_in_order() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::write(
+            base.join("shared/default.yaml"),
+            "timeout: 30\ncdn: global\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(base.join("shared/org")).unwrap();
+        std::fs::create_dir_all(base.join("shared/team")).unwrap();
+        std::fs::write(
+            base.join("shared/org/default.yaml"),
+            "region: eu\ntimeout: 10\nowner: org\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("shared/org/prod.yaml"), "timeout: 20\n").unwrap();
+        std::fs::write(
+            base.join("shared/team/default.yaml"),
+            "owner: team\nretries: 1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/my-app/project.yaml"),
+            "api_keys:\n  - key: test-key-123\nshared_groups: [org, team]\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/my-app/prod.yaml"), "retries: 5\n").unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        assert_eq!(center.list_shared_groups(), vec!["org", "team"]);
+        assert_eq!(center.list_shared_environments(), vec!["default"]);
+        assert_eq!(
+            center.list_shared_group_environments("org").unwrap(),
+            vec!["default", "prod"]
+        );
+        assert!(matches!(
+            center.list_shared_group_environments("missing"),
+            Err(ConfigError::SharedGroupNotFound(_))
+        ));
+        // 共享组目录不会被当作全局共享配置的环境
+        assert!(!center.storage.state().shared.contains_key("org"));
+
+        let merged = center.get_merged_config("my-app", "prod").unwrap();
+        assert_eq!(merged["region"], "eu");
+        assert_eq!(merged["timeout"], 20);
+        // team 在 org 之后声明，覆盖 org
+        assert_eq!(merged["owner"], "team");
+        // 项目覆盖所有共享组
+        assert_eq!(merged["retries"], 5);
+        // 全局 shared/ 仍在最底层
+        assert_eq!(merged["cdn"], "global");
+    }
+
+    #[test]
+    fn test_source_paths_follow_layers() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("shared/org")).unwrap();
+        std::fs::write(base.join("shared/org/prod.yaml"), "region: eu\n").unwrap();
+        std::fs::write(
+            base.join("projects/my-app/project.yaml"),
+            "api_keys:\n  - key: test-key-123\nshared_groups: [org]\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(base.join("projects/my-app/environments")).unwrap();
+        std::fs::write(
+            base.join("projects/my-app/environments/prod.yml"),
+            "replicas: 3\n",
+        )
+        .unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        assert_eq!(
+            center.source_paths("my-app", "prod").unwrap(),
+            vec![
+                "shared/default.yaml",
+                "shared/org/prod.yaml",
+                "projects/my-app/default.yaml",
+                "projects/my-app/environments/prod.yml",
+            ]
+        );
+        assert_eq!(
+            center.source_paths("my-app", "default").unwrap(),
+            vec!["shared/default.yaml", "projects/my-app/default.yaml"]
+        );
+        assert!(matches!(
+            center.source_paths("my-app", "missing"),
+            Err(ConfigError::EnvironmentNotFound(_))
+        ));
+
+        // 纯内存存储没有来源文件
+        let memory = ConfigCenter::from_state(center.storage.state().clone());
+        assert!(memory.source_paths("my-app", "prod").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_shared_nested_env_and_unreferenced_dir() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/my-app/regions")).unwrap();
+        std::fs::write(
+            base.join("projects/my-app/regions/us-east.yaml"),
+            "replicas: 3\n",
+        )
+        .unwrap();
+        // 未被引用的 shared/regions/ 与项目一样按嵌套环境名加载
+        std::fs::create_dir_all(base.join("shared/regions")).unwrap();
+        std::fs::write(
+            base.join("shared/regions/us-east.yaml"),
+            "zone: us-east-1a\ntimeout: 5\n",
+        )
+        .unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        assert!(center.list_shared_groups().is_empty());
+        assert_eq!(
+            center.list_shared_environments(),
+            vec!["default", "regions/us-east"]
+        );
+        let merged = center
+            .get_merged_config("my-app", "regions/us-east")
+            .unwrap();
+        assert_eq!(merged["zone"], "us-east-1a");
+        assert_eq!(merged["timeout"], 5);
+        assert_eq!(merged["replicas"], 3);
+        assert_eq!(
+            center.source_paths("my-app", "regions/us-east").unwrap(),
+            vec![
+                "shared/default.yaml",
+                "shared/regions/us-east.yaml",
+                "projects/my-app/default.yaml",
+                "projects/my-app/regions/us-east.yaml",
+            ]
+        );
+
+        // 项目引用后改为共享组，不再作为全局共享环境
+        std::fs::write(
+            base.join("projects/my-app/project.yaml"),
+            "api_keys:\n  - key: test-key-123\nshared_groups: [regions]\n",
+        )
+        .unwrap();
+        let reloaded = center
+            .reloaded_paths(&[base.join("projects/my-app/project.yaml")])
+            .unwrap();
+        assert_eq!(reloaded.list_shared_groups(), vec!["regions"]);
+        assert_eq!(reloaded.list_shared_environments(), vec!["default"]);
+        assert_eq!(
+            reloaded.storage.state(),
+            ConfigCenter::new(base).unwrap().storage.state()
+        );
+    }
+
+    #[test]
+    fn test_unknown_shared_group_warns() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/project.yaml"),
+            "api_keys:\n  - key: test-key-123\nshared_groups: [missing]\n",
+        )
+        .unwrap();
+
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        assert_eq!(center.load_warnings().len(), 1);
+        assert!(center.get_merged_config("my-app", "default").is_ok());
+
+        let strict = LoadOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(ConfigCenter::with_options(tmp.path(), &strict).is_err());
+    }
+
+    #[test]
+    fn test_checksum_independent_of_insertion_order() {
+        let build = |keys: &[&str]| {
+            let mut inner = serde_json::Map::new();
+            for key in keys {
+                inner.insert(key.to_string(), serde_json::json!(key.len()));
+            }
+            let mut config = MergedConfig::new();
+            for key in keys {
+                config.insert(
+                    key.to_string(),
+                    serde_json::json!([{"nested": inner.clone()}]),
+                );
+            }
+            config
+        };
+        let a = build(&["zeta", "alpha", "mid"]);
+        let b = build(&["mid", "zeta", "alpha"]);
+        assert_eq!(checksum(&a), checksum(&b));
+
+        let mut canonical = String::new();
+        write_canonical_object(a.iter(), &mut canonical);
+        assert!(
+            canonical.starts_with(r#"{"alpha":[{"nested":{"alpha":5,"mid":3,"zeta":4}}],"mid":"#)
+        );
+
+        let mut c = b.clone();
+        c.insert("alpha".to_string(), serde_json::json!(1));
+        assert_ne!(checksum(&a), checksum(&c));
+    }
+
+    #[test]
+    fn test_config_checksum() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+
+        let checksum = center.config_checksum("my-app", "default").unwrap();
+        assert_eq!(checksum.len(), 64);
+        assert!(checksum.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(
+            checksum,
+            center.config_checksum("my-app", "default").unwrap()
+        );
+
+        // 顺序不同、内容相同的配置校验和一致
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "log_level: debug\ndb_port: 5432\ndb_host: localhost\n",
+        )
+        .unwrap();
+        let reordered = ConfigCenter::new(tmp.path()).unwrap();
+        assert_eq!(
+            checksum,
+            reordered.config_checksum("my-app", "default").unwrap()
+        );
+
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "db_host: localhost\ndb_port: 5433\nlog_level: debug\n",
+        )
+        .unwrap();
+        let changed = ConfigCenter::new(tmp.path()).unwrap();
+        assert_ne!(
+            checksum,
+            changed.config_checksum("my-app", "default").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_incremental_reload_matches_full_reload() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/billing")).unwrap();
+        std::fs::write(base.join("projects/billing/default.yaml"), "x: 1\n").unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        // 修改一个环境、新增一个环境
+        let changed = base.join("projects/my-app/default.yaml");
+        std::fs::write(&changed, "db_host: db.internal\nlog_level: warn\n").unwrap();
+        let added = base.join("projects/my-app/prod.yaml");
+        std::fs::write(&added, "replicas: 3\n").unwrap();
+
+        let incremental = center.reloaded_paths(&[changed, added]).unwrap();
+        let full = ConfigCenter::new(base).unwrap();
+        assert_eq!(incremental.storage.state(), full.storage.state());
+        assert_eq!(incremental.revision(), center.revision() + 1);
+        assert_eq!(
+            incremental.source_files("my-app", "prod").unwrap(),
+            full.source_files("my-app", "prod").unwrap()
+        );
+        assert_eq!(
+            incremental.get_merged_config("my-app", "prod").unwrap()["db_host"],
+            serde_json::json!("db.internal")
+        );
+
+        // 删除项目目录
+        std::fs::remove_dir_all(base.join("projects/billing")).unwrap();
+        let removed = incremental
+            .reloaded_paths(&[base.join("projects/billing")])
+            .unwrap();
+        assert_eq!(removed.list_projects(), vec!["my-app"]);
+
+        // shared/ 变更退回整体重载
+        std::fs::write(base.join("shared/default.yaml"), "log_level: error\n").unwrap();
+        let reloaded = removed
+            .reloaded_paths(&[base.join("shared/default.yaml")])
+            .unwrap();
+        assert_eq!(
+            reloaded.storage.state(),
+            ConfigCenter::new(base).unwrap().storage.state()
+        );
+    }
+
+    #[test]
+    fn test_validate_env_reports_all_issues() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "timeout: \"30s\"\ndb_host: ${CONFIGAI_TEST_VALIDATE_UNSET}\ndb.host: other\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+
+        let issues = center.validate_env("my-app", "default").unwrap();
+        let kinds: Vec<IssueKind> = issues.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                IssueKind::UnresolvedVar,
+                IssueKind::TypeConflict,
+                IssueKind::EnvKeyCollision
+            ]
+        );
+        assert_eq!(
+            issues[0].message,
+            "db_host (my-app/default): ${CONFIGAI_TEST_VALIDATE_UNSET} 未设置"
+        );
+        assert_eq!(issues[2].message, "db.host, db_host 都会导出为 DB_HOST");
+
+        assert!(matches!(
+            center.validate_env("my-app", "missing"),
+            Err(ConfigError::EnvironmentNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_diff_envs() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::write(
+            base.join("projects/my-app/default.yaml"),
+            "db_host: localhost\npool: {max: 10, min: 1}\nflags: [a, b]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/my-app/prod.yaml"),
+            "db_host: db.prod\npool: {max: 10, min: 1}\nflags: [a, c]\nreplicas: 3\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/my-app/staging.yaml"), "replicas: 1\n").unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        let diff = center.diff_envs("my-app", "staging", "prod").unwrap();
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 3);
+        assert_eq!(
+            diff.changed["db_host"],
+            ValueChange {
+                from: serde_json::json!("localhost"),
+                to: serde_json::json!("db.prod"),
+            }
+        );
+        assert_eq!(diff.changed["flags"].to, serde_json::json!(["a", "c"]));
+        assert_eq!(diff.changed["replicas"].from, 1);
+        // 嵌套对象深比较相等，不算变化
+        assert!(!diff.changed.contains_key("pool"));
+
+        let diff = center.diff_envs("my-app", "default", "staging").unwrap();
+        assert_eq!(
+            diff.added,
+            HashMap::from([("replicas".to_string(), serde_json::json!(1))])
+        );
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+
+        let diff = center.diff_envs("my-app", "staging", "default").unwrap();
+        assert_eq!(diff.removed.len(), 1);
+        assert!(diff.removed.contains_key("replicas"));
+
+        assert!(center
+            .diff_envs("my-app", "prod", "prod")
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            center.diff_envs("my-app", "prod", "missing"),
+            Err(ConfigError::EnvironmentNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_merge_type_conflict_warnings() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::write(
+            base.join("shared/default.yaml"),
+            "timeout: 30\nretries: 3\ndb: {port: 5432}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/my-app/prod.yaml"),
+            "timeout: \"30s\"\nretries: 5\ndb: {port: \"5432\"}\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        let (merged, warnings) = center
+            .get_merged_config_with_warnings("my-app", "prod")
+            .unwrap();
+        // 合并结果不受影响
+        assert_eq!(merged["timeout"], "30s");
+        assert_eq!(merged["retries"], 5);
+        assert_eq!(
+            warnings,
+            vec![
+                "db.port: number (shared/default) 被 string (my-app/prod) 覆盖",
+                "timeout: number (shared/default) 被 string (my-app/prod) 覆盖",
+            ]
+        );
+
+        // 同类型覆盖不产生警告
+        let (_, warnings) = center
+            .get_merged_config_with_warnings("my-app", "default")
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_config_key_validation() {
+        use crate::storage::validate_key;
+
+        for key in [
+            "db_host",
+            "redis.url",
+            "api-timeout",
+            "V2",
+            "${REGION}_endpoint",
+        ] {
+            assert!(validate_key(key).is_ok(), "{}", key);
+        }
+        for key in ["db host", "a=b", "", "tab\tkey", "${REGION} x", "${open"] {
+            assert!(
+                matches!(validate_key(key), Err(ConfigError::InvalidKey(_))),
+                "{}",
+                key
+            );
+        }
+
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "db_host: localhost\n\"bad key\": 1\n\"a=b\": 2\n",
+        )
+        .unwrap();
+
+        // 宽松模式：保留已有数据，记录警告
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        assert_eq!(center.load_warnings().len(), 2);
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["bad key"], 1);
+
+        // 严格模式：拒绝
+        let err = ConfigCenter::with_options(
+            tmp.path(),
+            &LoadOptions {
+                strict: true,
+                ..Default::default()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ConfigError::InvalidKey(_)));
+    }
+
+    #[test]
+    fn test_export_project_ndjson() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::write(base.join("projects/my-app/prod.yaml"), "db_host: db.prod\n").unwrap();
+        std::fs::write(base.join("projects/my-app/staging.yaml"), "db_port: 6543\n").unwrap();
+        std::fs::create_dir_all(base.join("projects/empty")).unwrap();
+        let center = ConfigCenter::new(base).unwrap();
+
+        let ndjson = center.export_project_ndjson("my-app").unwrap();
+        let lines: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(
+            lines.len(),
+            center.list_environments("my-app").unwrap().len()
+        );
+        assert_eq!(lines[0]["env"], "default");
+        assert_eq!(lines[1]["env"], "prod");
+        assert_eq!(lines[1]["config"]["db_host"], "db.prod");
+        assert_eq!(lines[2]["config"]["db_port"], 6543);
+        assert_eq!(lines[2]["config"]["log_level"], "debug");
+
+        assert_eq!(center.export_project_ndjson("empty").unwrap(), "");
+        assert!(center.export_project_ndjson("missing").is_err());
+    }
+
+    #[test]
+    fn test_in_memory_state() {
+        use crate::models::ProjectData;
+
+        let mut state = ConfigState {
+            projects: HashMap::new(),
+            shared: HashMap::new(),
+            shared_groups: HashMap::new(),
+        };
+        state.shared.insert(
+            "default".to_string(),
+            HashMap::from([("log_level".to_string(), serde_json::json!("info"))]),
+        );
+        state.projects.insert(
+            "app".to_string(),
+            ProjectData {
+                meta: ProjectMeta {
+                    api_keys: vec![ApiKeyEntry {
+                        key: "k".to_string(),
+                        label: None,
+                    }],
+                    ..Default::default()
+                },
+                environments: HashMap::from([(
+                    "default".to_string(),
+                    HashMap::from([("db_port".to_string(), serde_json::json!(5432))]),
+                )]),
+            },
+        );
+
+        let center = ConfigCenter::from_state(state.clone());
+        let merged = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(merged["log_level"], "info");
+        assert_eq!(merged["db_port"], 5432);
+        assert_eq!(center.validate_api_key("k").unwrap().0, "app");
+
+        // 重载得到相同的内存状态，修订号递增
+        let reloaded = center.reloaded().unwrap();
+        assert_eq!(reloaded.revision(), 1);
+        assert_eq!(reloaded.storage.state(), &state);
+        assert!(reloaded.load_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_empty_config_dir() {
+        let tmp = TempDir::new().unwrap();
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        assert!(center.list_projects().is_empty());
+    }
+
+    #[test]
+    fn test_nonexistent_config_dir() {
+        let center = ConfigCenter::new(Path::new("/tmp/nonexistent_config_dir_12345")).unwrap();
+        assert!(center.list_projects().is_empty());
+    }
+
+    #[test]
+    fn test_malformed_yaml_skipped() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        // 故意写入无效 YAML
+        std::fs::write(base.join("projects/app/default.yaml"), "{{invalid yaml").unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        let projects = center.list_projects();
+        assert_eq!(projects.len(), 1);
+        // 环境配置加载失败，应该没有 default 环境
+        let state = center.storage.state();
+        assert!(state.projects["app"].environments.is_empty());
+        assert_eq!(center.load_warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_malformed_yaml() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/app/default.yaml"), "port: 3000\n").unwrap();
+        std::fs::write(base.join("projects/app/staging.yaml"), "{{invalid yaml").unwrap();
+
+        // 宽松模式：跳过无效文件，其余照常加载
+        let center = ConfigCenter::new(base).unwrap();
+        let merged = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(merged["port"], serde_json::json!(3000));
+
+        // 严格模式：直接报错
+        let err = ConfigCenter::with_options(
+            base,
+            &LoadOptions {
+                strict: true,
+                ..Default::default()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ConfigError::StorageError(_)));
+    }
+
+    #[test]
+    fn test_case_insensitive_project_collision() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/App")).unwrap();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        if std::fs::read_dir(base.join("projects")).unwrap().count() < 2 {
+            // 大小写不敏感的文件系统上无法构造该场景
+            return;
+        }
+
+        let storage = Storage::load(base).unwrap();
+        assert_eq!(storage.warnings().len(), 1);
+        let warning = &storage.warnings()[0];
+        assert!(warning.contains("projects/App"), "{}", warning);
+        assert!(warning.contains("projects/app"), "{}", warning);
+
+        let err = Storage::load_strict(base).err().unwrap();
+        assert!(matches!(err, ConfigError::StorageError(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_through_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        // 仿照 ConfigMap 挂载：真实数据在带时间戳的目录中，通过 ..data 符号链接间接引用
+        let tmp = TempDir::new().unwrap();
+        let real = tmp.path().join("..2024_01_01");
+        std::fs::create_dir_all(real.join("app")).unwrap();
+        std::fs::write(real.join("app/default.yaml"), "a: 1\n").unwrap();
+        std::fs::write(real.join("shared.yaml"), "log_level: info\n").unwrap();
+        symlink(&real, tmp.path().join("..data")).unwrap();
+
+        let config = tmp.path().join("config");
+        std::fs::create_dir_all(config.join("projects")).unwrap();
+        std::fs::create_dir_all(config.join("shared")).unwrap();
+        symlink(tmp.path().join("..data/app"), config.join("projects/app")).unwrap();
+        symlink(
+            tmp.path().join("..data/shared.yaml"),
+            config.join("shared/default.yaml"),
+        )
+        .unwrap();
+        let linked = tmp.path().join("linked-config");
+        symlink(&config, &linked).unwrap();
+
+        let center = ConfigCenter::new(&linked).unwrap();
+        let merged = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(merged["a"], serde_json::json!(1));
+        assert_eq!(merged["log_level"], serde_json::json!("info"));
+
+        // 通过真实路径报告的变更也能增量重载
+        std::fs::write(real.join("app/default.yaml"), "a: 2\n").unwrap();
+        let changed = config
+            .canonicalize()
+            .unwrap()
+            .join("projects/app/default.yaml");
+        let reloaded = center.reloaded_paths(&[changed]).unwrap();
+        assert_eq!(
+            reloaded.get_merged_config("app", "default").unwrap()["a"],
+            serde_json::json!(2)
+        );
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        // 每个文件恰好 16 字节
+        std::fs::write(base.join("projects/app/small.yaml"), "value: 123456789").unwrap();
+        std::fs::write(base.join("projects/app/large.yaml"), "value: 1234567890").unwrap();
+        let options = LoadOptions {
+            max_file_size: 16,
+            ..Default::default()
+        };
+
+        let storage = Storage::load_with(base, &options).unwrap();
+        let envs = &storage.state().projects["app"].environments;
+        assert!(envs.contains_key("small"));
+        assert!(!envs.contains_key("large"));
+        assert_eq!(storage.warnings().len(), 1);
+        assert!(storage.warnings()[0].contains("large.yaml"));
+
+        let strict = LoadOptions {
+            strict: true,
+            ..options
+        };
+        let err = Storage::load_with(base, &strict).err().unwrap();
+        assert!(matches!(err, ConfigError::StorageError(_)));
+
+        let default = Storage::load(base).unwrap();
+        assert_eq!(default.state().projects["app"].environments.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loop_in_project_dir() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let project = tmp.path().join("projects/my-app");
+        std::fs::create_dir_all(project.join("regions")).unwrap();
+        std::fs::write(project.join("regions/eu.yaml"), "region: eu\n").unwrap();
+        // 指回自身及上级目录的链接：不处理时环境名无限嵌套，两个链接时呈指数增长
+        symlink(".", project.join("loop")).unwrap();
+        symlink("..", project.join("regions/up")).unwrap();
+
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        assert_eq!(
+            center.list_environments("my-app").unwrap(),
+            vec!["default", "regions/eu"]
+        );
+        assert_eq!(center.load_warnings().len(), 2);
+
+        assert!(matches!(
+            ConfigCenter::with_options(
+                tmp.path(),
+                &LoadOptions {
+                    strict: true,
+                    ..Default::default()
+                }
+            ),
+            Err(ConfigError::StorageError(_))
+        ));
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_non_mapping_document() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("shared")).unwrap();
+        std::fs::write(base.join("shared/default.yaml"), "- a\n- b\n").unwrap();
+
+        assert!(Storage::load(base).is_ok());
+        let err = Storage::load_strict(base).err().unwrap();
+        assert!(matches!(err, ConfigError::StorageError(_)));
+    }
+
+    #[test]
+    fn test_reload() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/app/default.yaml"), "port: 3000\n").unwrap();
+
+        let mut center = ConfigCenter::new(base).unwrap();
+        let merged = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(merged["port"], serde_json::json!(3000));
+
+        // 修改文件
+        std::fs::write(base.join("projects/app/default.yaml"), "port: 8080\n").unwrap();
+        center.reload(base).unwrap();
+
+        let merged = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(merged["port"], serde_json::json!(8080));
+    }
+
+    #[test]
+    fn test_merged_cache_hit_returns_same_snapshot() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        let first = center.merged("my-app", "default").unwrap();
+        let second = center.merged("my-app", "default").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(
+            center.get_merged_config("my-app", "default").unwrap(),
+            *first
+        );
+    }
+
+    #[test]
+    fn test_reload_busts_merged_cache() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        std::fs::write(
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/app/default.yaml"), "port: 3000\n").unwrap();
+
+        let mut center = ConfigCenter::new(base).unwrap();
+        assert_eq!(center.revision(), 0);
+        let before = center.merged("app", "default").unwrap();
+
+        std::fs::write(base.join("projects/app/default.yaml"), "port: 8080\n").unwrap();
+        center.reload(base).unwrap();
+        assert_eq!(center.revision(), 1);
+
+        let after = center.merged("app", "default").unwrap();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert_eq!(after["port"], serde_json::json!(8080));
+
+        let next = center.reloaded().unwrap();
+        assert_eq!(next.revision(), 2);
+        assert_eq!(
+            next.get_merged_config("app", "default").unwrap()["port"],
+            8080
+        );
+    }
+
+    #[test]
+    fn test_multiple_projects() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app1")).unwrap();
+        std::fs::create_dir_all(base.join("projects/app2")).unwrap();
+        std::fs::write(
+            base.join("projects/app1/project.yaml"),
+            "description: app1\napi_keys:\n  - key: key1\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/app1/default.yaml"), "port: 3000\n").unwrap();
+        std::fs::write(
+            base.join("projects/app2/project.yaml"),
+            "description: app2\napi_keys:\n  - key: key2\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("projects/app2/default.yaml"), "port: 4000\n").unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        let mut projects = center.list_projects();
+        projects.sort();
+        assert_eq!(projects, vec!["app1", "app2"]);
+
+        let (proj, _) = center.validate_api_key("key1").unwrap();
+        assert_eq!(proj, "app1");
+        let (proj, _) = center.validate_api_key("key2").unwrap();
+        assert_eq!(proj, "app2");
+    }
+
+    #[test]
+    fn test_list_environments_sorted() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        for env in ["production", "default", "staging"] {
+            std::fs::write(base.join(format!("projects/app/{}.yaml", env)), "port: 1\n").unwrap();
+        }
+
+        let center = ConfigCenter::new(base).unwrap();
+        assert_eq!(
+            center.list_environments("app").unwrap(),
+            vec!["default", "production", "staging"]
+        );
+        assert!(matches!(
+            center.list_environments("nope").unwrap_err(),
+            ConfigError::ProjectNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_list_all_environments() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let base = tmp.path();
+        std::fs::write(base.join("projects/my-app/prod.yaml"), "a: 1\n").unwrap();
+        std::fs::create_dir_all(base.join("projects/billing")).unwrap();
+        std::fs::write(base.join("projects/billing/staging.yaml"), "a: 1\n").unwrap();
+        std::fs::write(base.join("projects/billing/default.yaml"), "a: 1\n").unwrap();
+        std::fs::create_dir_all(base.join("projects/empty")).unwrap();
+
+        let center = ConfigCenter::new(base).unwrap();
+        let pair = |p: &str, e: &str| (p.to_string(), e.to_string());
+        assert_eq!(
+            center.list_all_environments(),
+            vec![
+                pair("billing", "default"),
+                pair("billing", "staging"),
+                pair("my-app", "default"),
+                pair("my-app", "prod"),
+            ]
+        );
     }
 
     #[test]
-    fn test_env_export_format() {
+    fn test_multiple_environments() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
+        std::fs::create_dir_all(base.join("shared")).unwrap();
         std::fs::create_dir_all(base.join("projects/app")).unwrap();
         std::fs::write(
             base.join("projects/app/project.yaml"),
             "api_keys:\n  - key: k\n",
         )
         .unwrap();
-        std::fs::write(
-            base.join("projects/app/default.yaml"),
-            "db_host: localhost\ndb_port: 5432\n",
-        )
-        .unwrap();
+        std::fs::write(base.join("projects/app/default.yaml"), "port: 3000\n").unwrap();
+        std::fs::write(base.join("projects/app/production.yaml"), "port: 80\n").unwrap();
+        std::fs::write(base.join("shared/default.yaml"), "log_level: info\n").unwrap();
+        std::fs::write(base.join("shared/production.yaml"), "log_level: warn\n").unwrap();
 
         let center = ConfigCenter::new(base).unwrap();
-        let export = center.get_env_export("app", "default", None).unwrap();
 
-        assert!(export.contains("export DB_HOST=localhost"));
-        assert!(export.contains("export DB_PORT=5432"));
+        let default_cfg = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(default_cfg["port"], serde_json::json!(3000));
+        assert_eq!(default_cfg["log_level"], serde_json::json!("info"));
+
+        let prod_cfg = center.get_merged_config("app", "production").unwrap();
+        assert_eq!(prod_cfg["port"], serde_json::json!(80));
+        // production.yaml 覆盖 shared/default.yaml 的 log_level
+        assert_eq!(prod_cfg["log_level"], serde_json::json!("warn"));
     }
 
     #[test]
-    fn test_env_export_quoting() {
+    fn test_complex_yaml_values() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
         std::fs::create_dir_all(base.join("projects/app")).unwrap();
@@ -468,31 +3007,23 @@ mod tests {
         .unwrap();
         std::fs::write(
             base.join("projects/app/default.yaml"),
-            "greeting: hello world\n",
+            "hosts:\n  - a\n  - b\ndb:\n  host: localhost\n  port: 5432\nenabled: true\ncount: 42\n",
         )
         .unwrap();
 
         let center = ConfigCenter::new(base).unwrap();
-        let export = center.get_env_export("app", "default", None).unwrap();
-
-        assert!(export.contains("export GREETING=\"hello world\""));
-    }
-
-    #[test]
-    fn test_empty_config_dir() {
-        let tmp = TempDir::new().unwrap();
-        let center = ConfigCenter::new(tmp.path()).unwrap();
-        assert!(center.list_projects().is_empty());
-    }
+        let merged = center.get_merged_config("app", "default").unwrap();
 
-    #[test]
-    fn test_nonexistent_config_dir() {
-        let center = ConfigCenter::new(Path::new("/tmp/nonexistent_config_dir_12345")).unwrap();
-        assert!(center.list_projects().is_empty());
+        assert_eq!(merged["hosts"], serde_json::json!(["a", "b"]));
+        assert_eq!(
+            merged["db"],
+            serde_json::json!({"host": "localhost", "port": 5432})
+        );
+        assert_eq!(merged["enabled"], serde_json::json!(true));
+        assert_eq!(merged["count"], serde_json::json!(42));
     }
-
     #[test]
-    fn test_malformed_yaml_skipped() {
+    fn test_special_floats_preserved_as_strings() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
         std::fs::create_dir_all(base.join("projects/app")).unwrap();
@@ -501,19 +3032,31 @@ mod tests {
             "api_keys:\n  - key: k\n",
         )
         .unwrap();
-        // 故意写入无效 YAML
-        std::fs::write(base.join("projects/app/default.yaml"), "{{invalid yaml").unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "pos: .inf\nneg: -.inf\nnan: .nan\nratio: 0.5\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/staging.toml"),
+            "pos = inf\nnan = nan\n",
+        )
+        .unwrap();
 
         let center = ConfigCenter::new(base).unwrap();
-        let projects = center.list_projects();
-        assert_eq!(projects.len(), 1);
-        // 环境配置加载失败，应该没有 default 环境
-        let state = center.storage.state();
-        assert!(state.projects["app"].environments.is_empty());
+        let merged = center.get_merged_config("app", "default").unwrap();
+        assert_eq!(merged["pos"], "Infinity");
+        assert_eq!(merged["neg"], "-Infinity");
+        assert_eq!(merged["nan"], "NaN");
+        assert_eq!(merged["ratio"], 0.5);
+
+        let merged = center.get_merged_config("app", "staging").unwrap();
+        assert_eq!(merged["pos"], "Infinity");
+        assert_eq!(merged["nan"], "NaN");
     }
 
     #[test]
-    fn test_reload() {
+    fn test_large_unsigned_integer_preserved() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
         std::fs::create_dir_all(base.join("projects/app")).unwrap();
@@ -522,82 +3065,109 @@ mod tests {
             "api_keys:\n  - key: k\n",
         )
         .unwrap();
-        std::fs::write(base.join("projects/app/default.yaml"), "port: 3000\n").unwrap();
+        std::fs::write(
+            base.join("projects/app/default.yaml"),
+            "max_id: 18446744073709551615\nsnowflake: 9223372036854775808\n",
+        )
+        .unwrap();
 
-        let mut center = ConfigCenter::new(base).unwrap();
+        let center = ConfigCenter::new(base).unwrap();
         let merged = center.get_merged_config("app", "default").unwrap();
-        assert_eq!(merged["port"], serde_json::json!(3000));
 
-        // 修改文件
-        std::fs::write(base.join("projects/app/default.yaml"), "port: 8080\n").unwrap();
-        center.reload(base).unwrap();
+        assert_eq!(merged["max_id"], serde_json::json!(u64::MAX));
+        assert_eq!(merged["max_id"].as_u64(), Some(u64::MAX));
+        assert!(!merged["max_id"].is_f64());
+        assert_eq!(
+            merged["snowflake"].as_u64(),
+            Some(9_223_372_036_854_775_808)
+        );
 
-        let merged = center.get_merged_config("app", "default").unwrap();
-        assert_eq!(merged["port"], serde_json::json!(8080));
+        let export = center
+            .get_env_export("app", "default", &ExportOptions::default())
+            .unwrap();
+        assert!(export.contains("export MAX_ID=18446744073709551615"));
     }
 
     #[test]
-    fn test_multiple_projects() {
+    fn test_yaml_anchors_and_merge_keys() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
-        std::fs::create_dir_all(base.join("projects/app1")).unwrap();
-        std::fs::create_dir_all(base.join("projects/app2")).unwrap();
+        std::fs::create_dir_all(base.join("projects/app")).unwrap();
         std::fs::write(
-            base.join("projects/app1/project.yaml"),
-            "description: app1\napi_keys:\n  - key: key1\n",
+            base.join("projects/app/project.yaml"),
+            "api_keys:\n  - key: k\n",
         )
         .unwrap();
-        std::fs::write(base.join("projects/app1/default.yaml"), "port: 3000\n").unwrap();
         std::fs::write(
-            base.join("projects/app2/project.yaml"),
-            "description: app2\napi_keys:\n  - key: key2\n",
+            base.join("projects/app/default.yaml"),
+            "db_defaults: &db\n  host: localhost\n  port: 5432\n\
+             primary:\n  <<: *db\n  port: 6432\n\
+             replica: *db\n\
+             pool:\n  <<: [*db]\n  size: 10\n",
         )
         .unwrap();
-        std::fs::write(base.join("projects/app2/default.yaml"), "port: 4000\n").unwrap();
 
         let center = ConfigCenter::new(base).unwrap();
-        let mut projects = center.list_projects();
-        projects.sort();
-        assert_eq!(projects, vec!["app1", "app2"]);
+        let merged = center.get_merged_config("app", "default").unwrap();
 
-        let (proj, _) = center.validate_api_key("key1").unwrap();
-        assert_eq!(proj, "app1");
-        let (proj, _) = center.validate_api_key("key2").unwrap();
-        assert_eq!(proj, "app2");
+        // 合并键：继承锚点字段，本地字段优先
+        assert_eq!(
+            merged["primary"],
+            serde_json::json!({"host": "localhost", "port": 6432})
+        );
+        // 别名：完整复制锚点内容
+        assert_eq!(
+            merged["replica"],
+            serde_json::json!({"host": "localhost", "port": 5432})
+        );
+        assert_eq!(
+            merged["pool"],
+            serde_json::json!({"host": "localhost", "port": 5432, "size": 10})
+        );
+        assert!(merged["primary"].get("<<").is_none());
     }
 
     #[test]
-    fn test_multiple_environments() {
+    fn test_nested_environment_directories() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
-        std::fs::create_dir_all(base.join("shared")).unwrap();
-        std::fs::create_dir_all(base.join("projects/app")).unwrap();
+        let app = base.join("projects/app");
+        std::fs::create_dir_all(app.join("environments/regions")).unwrap();
+        std::fs::create_dir_all(app.join("regions")).unwrap();
+        std::fs::write(app.join("project.yaml"), "api_keys:\n  - key: k\n").unwrap();
+        std::fs::write(app.join("default.yaml"), "port: 3000\n").unwrap();
+        std::fs::write(app.join("environments/staging.yaml"), "port: 4000\n").unwrap();
         std::fs::write(
-            base.join("projects/app/project.yaml"),
-            "api_keys:\n  - key: k\n",
+            app.join("environments/regions/eu-west.yaml"),
+            "region: eu-west-1\n",
         )
         .unwrap();
-        std::fs::write(base.join("projects/app/default.yaml"), "port: 3000\n").unwrap();
-        std::fs::write(base.join("projects/app/production.yaml"), "port: 80\n").unwrap();
-        std::fs::write(base.join("shared/default.yaml"), "log_level: info\n").unwrap();
-        std::fs::write(base.join("shared/production.yaml"), "log_level: warn\n").unwrap();
+        std::fs::write(app.join("regions/us-east.yaml"), "region: us-east-1\n").unwrap();
 
         let center = ConfigCenter::new(base).unwrap();
+        let mut envs: Vec<&String> = center.storage.state().projects["app"]
+            .environments
+            .keys()
+            .collect();
+        envs.sort();
+        assert_eq!(
+            envs,
+            vec!["default", "regions/eu-west", "regions/us-east", "staging"]
+        );
 
-        let default_cfg = center.get_merged_config("app", "default").unwrap();
-        assert_eq!(default_cfg["port"], serde_json::json!(3000));
-        assert_eq!(default_cfg["log_level"], serde_json::json!("info"));
+        let staging = center.get_merged_config("app", "staging").unwrap();
+        assert_eq!(staging["port"], serde_json::json!(4000));
 
-        let prod_cfg = center.get_merged_config("app", "production").unwrap();
-        assert_eq!(prod_cfg["port"], serde_json::json!(80));
-        // production.yaml 覆盖 shared/default.yaml 的 log_level
-        assert_eq!(prod_cfg["log_level"], serde_json::json!("warn"));
+        let us_east = center.get_merged_config("app", "regions/us-east").unwrap();
+        assert_eq!(us_east["region"], serde_json::json!("us-east-1"));
+        assert_eq!(us_east["port"], serde_json::json!(3000));
     }
 
     #[test]
-    fn test_complex_yaml_values() {
+    fn test_json_and_toml_env_files() {
         let tmp = TempDir::new().unwrap();
         let base = tmp.path();
+        std::fs::create_dir_all(base.join("shared")).unwrap();
         std::fs::create_dir_all(base.join("projects/app")).unwrap();
         std::fs::write(
             base.join("projects/app/project.yaml"),
@@ -606,21 +3176,38 @@ mod tests {
         .unwrap();
         std::fs::write(
             base.join("projects/app/default.yaml"),
-            "hosts:\n  - a\n  - b\ndb:\n  host: localhost\n  port: 5432\nenabled: true\ncount: 42\n",
+            "db:\n  host: localhost\n  port: 5432\nhosts:\n  - a\n  - b\nenabled: true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/staging.json"),
+            r#"{"db": {"host": "localhost", "port": 5432}, "hosts": ["a", "b"], "enabled": true}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("projects/app/production.toml"),
+            "hosts = [\"a\", \"b\"]\nenabled = true\n\n[db]\nhost = \"localhost\"\nport = 5432\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.join("shared/production.toml"),
+            "log_level = \"warn\"\n",
         )
         .unwrap();
 
         let center = ConfigCenter::new(base).unwrap();
-        let merged = center.get_merged_config("app", "default").unwrap();
+        let yaml_cfg = center.get_merged_config("app", "default").unwrap();
+        let json_cfg = center.get_merged_config("app", "staging").unwrap();
+        let mut toml_cfg = center.get_merged_config("app", "production").unwrap();
 
-        assert_eq!(merged["hosts"], serde_json::json!(["a", "b"]));
+        assert_eq!(json_cfg, yaml_cfg);
         assert_eq!(
-            merged["db"],
-            serde_json::json!({"host": "localhost", "port": 5432})
+            toml_cfg.remove("log_level"),
+            Some(serde_json::json!("warn"))
         );
-        assert_eq!(merged["enabled"], serde_json::json!(true));
-        assert_eq!(merged["count"], serde_json::json!(42));
+        assert_eq!(toml_cfg, yaml_cfg);
     }
+
     #[test]
     fn test_env_var_substitution() {
         std::env::set_var("TEST_DB_PASSWORD", "secret123");
@@ -738,19 +3325,298 @@ mod tests {
         std::env::remove_var("TEST_PORT");
     }
 
+    #[test]
+    fn test_merged_config_with_injected_vars() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "url: \"postgres://${TEST_INJECTED_HOST}:${TEST_INJECTED_PORT}\"\nname: \"${TEST_INJECTED_HOST}\"\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+
+        let vars = HashMap::from([("TEST_INJECTED_HOST".to_string(), "db.internal".to_string())]);
+        let merged = center
+            .get_merged_config_with_vars("my-app", "default", &vars)
+            .unwrap();
+        assert_eq!(
+            merged["url"],
+            "postgres://db.internal:${TEST_INJECTED_PORT}"
+        );
+        assert_eq!(merged["name"], "db.internal");
+        assert!(std::env::var("TEST_INJECTED_HOST").is_err());
+
+        // 注入的变量不进入缓存，默认方法仍使用进程环境
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["name"], "${TEST_INJECTED_HOST}");
+    }
+
+    #[test]
+    fn test_file_reference_substitution() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let secrets = TempDir::new().unwrap();
+        std::fs::write(secrets.path().join("db_password"), "s3cret\n").unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("other"), "nope").unwrap();
+
+        let write_default = |content: String| {
+            std::fs::write(tmp.path().join("projects/my-app/default.yaml"), content).unwrap();
+        };
+        let center = |tmp: &TempDir| {
+            ConfigCenter::new(tmp.path())
+                .unwrap()
+                .with_secrets_dir(Some(secrets.path().to_path_buf()))
+        };
+
+        // 绝对路径与相对路径（相对于允许目录）
+        write_default(format!(
+            "password: \"${{file:{}}}\"\ndsn: \"user:${{file:db_password}}@db\"\n",
+            secrets.path().join("db_password").display()
+        ));
+        let merged = center(&tmp).get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["password"], "s3cret");
+        assert_eq!(merged["dsn"], "user:s3cret@db");
+
+        write_default("password: \"${file:missing}\"\n".to_string());
+        let err = center(&tmp)
+            .get_merged_config("my-app", "default")
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::FileReference(_)));
+
+        for path in [
+            outside.path().join("other").display().to_string(),
+            format!(
+                "../{}/other",
+                outside.path().file_name().unwrap().to_string_lossy()
+            ),
+        ] {
+            write_default(format!("password: \"${{file:{}}}\"\n", path));
+            let err = center(&tmp)
+                .get_merged_config("my-app", "default")
+                .unwrap_err();
+            assert!(err.to_string().contains("outside the allowed directory"));
+        }
+
+        // 未配置允许目录时禁用
+        let plain = ConfigCenter::new(tmp.path()).unwrap();
+        let err = plain.get_merged_config("my-app", "default").unwrap_err();
+        assert!(err.to_string().contains("disabled"));
+    }
+
+    #[test]
+    fn test_file_value_reference() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let secrets = TempDir::new().unwrap();
+        let pem = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----";
+        std::fs::create_dir_all(secrets.path().join("certs")).unwrap();
+        std::fs::write(
+            secrets.path().join("certs/server.pem"),
+            format!("{}\n", pem),
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "cert: \"@file:./certs/server.pem\"\nhandle: \"@@file:literal\"\nmention: \"@team\"\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(tmp.path())
+            .unwrap()
+            .with_secrets_dir(Some(secrets.path().to_path_buf()));
+
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["cert"], pem);
+        assert_eq!(merged["handle"], "@file:literal");
+        assert_eq!(merged["mention"], "@team");
+
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "cert: \"@file:./certs/missing.pem\"\n",
+        )
+        .unwrap();
+        let err = center
+            .reloaded()
+            .unwrap()
+            .get_merged_config("my-app", "default")
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::FileReference(_)));
+    }
+
+    #[test]
+    fn test_rotated_secret_file_not_cached() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        let secrets = TempDir::new().unwrap();
+        std::fs::write(secrets.path().join("token"), "old\n").unwrap();
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            "token: \"${file:token}\"\ncert: \"@file:token\"\n",
+        )
+        .unwrap();
+        let center = ConfigCenter::new(tmp.path())
+            .unwrap()
+            .with_secrets_dir(Some(secrets.path().to_path_buf()));
+
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["token"], "old");
+
+        // 轮换密钥文件：无需重载配置即可读到新值
+        std::fs::write(secrets.path().join("token"), "new\n").unwrap();
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["token"], "new");
+        assert_eq!(merged["cert"], "new");
+        assert!(center.merged_cache.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            concat!(
+                "db_host: db.internal\n",
+                "db_port: 5432\n",
+                "db_addr: \"{{ db_host }}:{{ db_port }}\"\n",
+                "replicas: [\"{% for i in [1, 2] %}r{{ i }}{% if not loop.last %},{% endif %}{% endfor %}\"]\n",
+            ),
+        )
+        .unwrap();
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+
+        let rendered = center.get_rendered_config("my-app", "default").unwrap();
+        assert_eq!(rendered["db_addr"], "db.internal:5432");
+        assert_eq!(rendered["replicas"], serde_json::json!(["r1,r2"]));
+        // 不请求渲染时保留原始模板
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        assert_eq!(merged["db_addr"], "{{ db_host }}:{{ db_port }}");
+
+        let options = ExportOptions {
+            render: true,
+            ..Default::default()
+        };
+        let export = center
+            .get_env_export("my-app", "default", &options)
+            .unwrap();
+        assert!(export.contains("export DB_ADDR=db.internal:5432"));
+    }
+
+    #[test]
+    fn test_render_template_missing_variable() {
+        let mut config = HashMap::new();
+        config.insert("url".to_string(), serde_json::json!("{{ missing_host }}"));
+        let err = render_templates(&config).unwrap_err();
+        assert!(matches!(err, ConfigError::Template(_)));
+        assert!(err.to_string().contains("missing_host"));
+    }
+
     #[test]
     fn test_substitute_env_in_string() {
         std::env::set_var("TEST_SUB_A", "hello");
-        assert_eq!(substitute_env_in_string("${TEST_SUB_A}"), "hello");
+        assert_eq!(substitute("${TEST_SUB_A}"), "hello");
         assert_eq!(
-            substitute_env_in_string("prefix_${TEST_SUB_A}_suffix"),
+            substitute("prefix_${TEST_SUB_A}_suffix"),
             "prefix_hello_suffix"
         );
-        assert_eq!(substitute_env_in_string("no vars here"), "no vars here");
-        assert_eq!(
-            substitute_env_in_string("${MISSING_VAR_XYZ}"),
-            "${MISSING_VAR_XYZ}"
-        );
+        assert_eq!(substitute("no vars here"), "no vars here");
+        assert_eq!(substitute("${MISSING_VAR_XYZ}"), "${MISSING_VAR_XYZ}");
         std::env::remove_var("TEST_SUB_A");
     }
+
+    #[test]
+    fn test_env_var_substitution_in_keys() {
+        std::env::set_var("TEST_KEY_REGION", "us");
+        let tmp = TempDir::new().unwrap();
+        setup_config_dir(&tmp);
+        std::fs::write(
+            tmp.path().join("projects/my-app/default.yaml"),
+            concat!(
+                "\"${TEST_KEY_REGION}_endpoint\": https://us.example.com\n",
+                "\"${TEST_KEY_UNSET}_endpoint\": literal\n",
+                "regions:\n",
+                "  \"${TEST_KEY_REGION}\": from-var\n",
+                "  us: from-literal\n",
+            ),
+        )
+        .unwrap();
+
+        let center = ConfigCenter::new(tmp.path()).unwrap();
+        let merged = center.get_merged_config("my-app", "default").unwrap();
+        std::env::remove_var("TEST_KEY_REGION");
+
+        assert_eq!(merged["us_endpoint"], "https://us.example.com");
+        assert_eq!(merged["${TEST_KEY_UNSET}_endpoint"], "literal");
+        // 重名时按原 key 字典序后者覆盖：`${...}` 排在 `us` 之前
+        assert_eq!(merged["regions"], serde_json::json!({"us": "from-literal"}));
+    }
+
+    #[test]
+    fn test_substitute_env_pathological_inputs() {
+        std::env::set_var("TEST_PATHO_A", "a");
+        std::env::set_var("TEST_PATHO_B", "b");
+        let cases = [
+            ("${}", "${}"),
+            ("${}${}", "${}${}"),
+            ("${", "${"),
+            ("}${", "}${"),
+            ("$", "$"),
+            ("${${TEST_PATHO_A}}", "${a}"),
+            ("${TEST_PATHO_A}${TEST_PATHO_B}", "ab"),
+            ("${}${TEST_PATHO_A}", "${}a"),
+            ("${TEST_PATHO_A}${", "a${"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(substitute(input), expected, "input: {}", input);
+        }
+        std::env::remove_var("TEST_PATHO_A");
+        std::env::remove_var("TEST_PATHO_B");
+    }
+
+    #[test]
+    fn test_substitute_env_bracket_matching() {
+        std::env::set_var("TEST_BRACKET_A", "a");
+        std::env::set_var("TEST_BRACKET_B", "b");
+        let cases = [
+            // 未闭合的 ${ 不影响前面的替换
+            (
+                "ok ${TEST_BRACKET_A} bad ${TEST_BRACKET_B",
+                "ok a bad ${TEST_BRACKET_B",
+            ),
+            // 嵌套形式只替换最内层，结果不再二次扫描
+            ("${TEST_BRACKET_A${TEST_BRACKET_B}}", "${TEST_BRACKET_Ab}"),
+            // 交替出现的闭合/未闭合引用
+            (
+                "${TEST_BRACKET_A}${${TEST_BRACKET_B}${TEST_BRACKET_A",
+                "a${b${TEST_BRACKET_A",
+            ),
+            ("{${TEST_BRACKET_A}}", "{a}"),
+            ("$${TEST_BRACKET_A}", "$a"),
+            ("${TEST_BRACKET_A}}", "a}"),
+            ("${TEST_BRACKET_{A}", "${TEST_BRACKET_{A}"),
+            (
+                "${TEST_BRACKET_MISSING}${TEST_BRACKET_B}",
+                "${TEST_BRACKET_MISSING}b",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(substitute(input), expected, "input: {}", input);
+        }
+
+        // 变量值中的 ${...} 不会被再次替换
+        std::env::set_var("TEST_BRACKET_INDIRECT", "${TEST_BRACKET_A}");
+        assert_eq!(substitute("${TEST_BRACKET_INDIRECT}"), "${TEST_BRACKET_A}");
+        std::env::remove_var("TEST_BRACKET_A");
+        std::env::remove_var("TEST_BRACKET_B");
+        std::env::remove_var("TEST_BRACKET_INDIRECT");
+    }
+
+    proptest::proptest! {
+        /// 任意由 `$`、`{`、`}` 组成的输入都能终止；不引用已设置的变量时原样返回
+        #[test]
+        fn prop_substitute_env_terminates(input in "[${}xyz~]{0,24}") {
+            proptest::prop_assert_eq!(substitute(&input), input);
+        }
+    }
 }
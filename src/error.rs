@@ -9,12 +9,33 @@ pub enum ConfigError {
     #[error("config item not found: {0}")]
     ConfigItemNotFound(String),
 
+    #[error("shared group not found: {0}")]
+    SharedGroupNotFound(String),
+
+    #[error("invalid config key: {0:?}")]
+    InvalidKey(String),
+
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    #[error("key conflict: {0}")]
+    KeyConflict(String),
+
+    #[error("file reference error: {0}")]
+    FileReference(String),
+
+    #[error("template error: {0}")]
+    Template(String),
+
     #[error("unauthorized: {0}")]
     Unauthorized(String),
 
     #[error("forbidden: {0}")]
     Forbidden(String),
 
+    #[error("rate limited: retry after {0}s")]
+    RateLimited(u64),
+
     #[error("storage error: {0}")]
     StorageError(String),
 
@@ -22,4 +43,26 @@ pub enum ConfigError {
     IoError(#[from] std::io::Error),
 }
 
+impl ConfigError {
+    /// 机器可读的错误码，作为 API 错误响应的稳定契约
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::ProjectNotFound(_) => "PROJECT_NOT_FOUND",
+            ConfigError::EnvironmentNotFound(_) => "ENVIRONMENT_NOT_FOUND",
+            ConfigError::ConfigItemNotFound(_) => "CONFIG_ITEM_NOT_FOUND",
+            ConfigError::SharedGroupNotFound(_) => "SHARED_GROUP_NOT_FOUND",
+            ConfigError::InvalidKey(_) => "INVALID_KEY",
+            ConfigError::InvalidParameter(_) => "INVALID_PARAMETER",
+            ConfigError::KeyConflict(_) => "KEY_CONFLICT",
+            ConfigError::FileReference(_) => "FILE_REFERENCE",
+            ConfigError::Template(_) => "TEMPLATE",
+            ConfigError::Unauthorized(_) => "UNAUTHORIZED",
+            ConfigError::Forbidden(_) => "FORBIDDEN",
+            ConfigError::RateLimited(_) => "RATE_LIMITED",
+            ConfigError::StorageError(_) => "STORAGE_ERROR",
+            ConfigError::IoError(_) => "IO_ERROR",
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, ConfigError>;
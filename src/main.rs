@@ -4,24 +4,190 @@ pub mod error;
 pub mod models;
 pub mod storage;
 
-fn main() {
-    tracing_subscriber::fmt::init();
+use tracing_subscriber::filter::LevelFilter;
 
+fn main() {
     let args: Vec<String> = std::env::args().collect();
+    init_logging(
+        parse_log_format(parse_arg(&args, "--log-format").as_deref()),
+        parse_log_level(parse_arg(&args, "--log-level").as_deref()),
+    );
+
     let command = args.get(1).map(|s| s.as_str()).unwrap_or("serve");
 
     let config_dir = parse_arg(&args, "--config-dir").unwrap_or_else(|| "./config".to_string());
-    let port = parse_arg(&args, "--port").unwrap_or_else(|| "3000".to_string());
+    let options = storage::LoadOptions {
+        strict: has_flag(&args, "--strict"),
+        max_file_size: parse_max_file_size(parse_arg(&args, "--max-file-size").as_deref())
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }),
+    };
 
     match command {
-        "init" => init(&config_dir),
+        "init" => init(&config_dir, &options),
         _ => {
+            let serve_args = ServeArgs {
+                port: parse_arg(&args, "--port").unwrap_or_else(|| "3000".to_string()),
+                router_options: api::RouterOptions {
+                    cors_origins: parse_arg(&args, "--cors-origins")
+                        .or_else(|| std::env::var("CONFIGAI_CORS_ORIGINS").ok())
+                        .map(|v| split_list(&v))
+                        .unwrap_or_default(),
+                    request_timeout: parse_request_timeout(
+                        parse_arg(&args, "--request-timeout").as_deref(),
+                    )
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        std::process::exit(2);
+                    }),
+                    read_only: has_flag(&args, "--read-only"),
+                },
+                rate_limit: parse_rate_limit(
+                    parse_arg(&args, "--rate-limit").as_deref(),
+                    parse_arg(&args, "--rate-limit-burst").as_deref(),
+                )
+                .unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }),
+                audit_log: parse_arg(&args, "--audit-log"),
+                secrets_dir: parse_arg(&args, "--secrets-dir")
+                    .or_else(|| std::env::var("CONFIGAI_SECRETS_DIR").ok()),
+                config_dir,
+                options,
+            };
             let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(serve(&config_dir, &port));
+            rt.block_on(serve(&serve_args));
+        }
+    }
+}
+
+/// serve 子命令参数
+struct ServeArgs {
+    config_dir: String,
+    port: String,
+    options: storage::LoadOptions,
+    router_options: api::RouterOptions,
+    /// 每个 API Key 每秒请求数及突发容量（未设置则不限流）
+    rate_limit: Option<(f64, f64)>,
+    /// 审计日志文件路径
+    audit_log: Option<String>,
+    /// `${file:...}` 允许读取的目录
+    secrets_dir: Option<String>,
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    /// 人类可读文本（默认）
+    Text,
+    /// 每行一个 JSON 对象，便于日志采集
+    Json,
+}
+
+/// 解析 `--log-format`；未知取值时回退为文本格式
+fn parse_log_format(value: Option<&str>) -> LogFormat {
+    match value {
+        Some(v) if v.eq_ignore_ascii_case("json") => LogFormat::Json,
+        Some(v) if !v.eq_ignore_ascii_case("text") => {
+            eprintln!("Unknown --log-format {:?}, using text", v);
+            LogFormat::Text
         }
+        _ => LogFormat::Text,
+    }
+}
+
+/// 解析 `--log-level`（trace/debug/info/warn/error）；未设置或未知取值时为 info
+fn parse_log_level(value: Option<&str>) -> LevelFilter {
+    match value.map(|v| v.parse::<LevelFilter>()) {
+        None => LevelFilter::INFO,
+        Some(Ok(level)) => level,
+        Some(Err(_)) => {
+            eprintln!(
+                "Unknown --log-level {:?}, using info",
+                value.unwrap_or_default()
+            );
+            LevelFilter::INFO
+        }
+    }
+}
+
+/// 初始化 tracing 订阅者：`level` 为默认级别，RUST_LOG 中的指令（如 `configai::api=debug`）优先
+fn init_logging(format: LogFormat, level: LevelFilter) {
+    let filter = tracing_subscriber::EnvFilter::builder()
+        .with_default_directive(level.into())
+        .from_env_lossy();
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// 解析 `--request-timeout`（正整数秒）；未设置时为默认值，0 或无法解析时报错
+fn parse_request_timeout(value: Option<&str>) -> Result<std::time::Duration, String> {
+    let Some(value) = value else {
+        return Ok(api::DEFAULT_REQUEST_TIMEOUT);
+    };
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(std::time::Duration::from_secs(secs)),
+        _ => Err(format!(
+            "Invalid --request-timeout {:?}: expected a positive number of seconds",
+            value
+        )),
+    }
+}
+
+/// 解析 `--max-file-size`（正整数字节）；未设置时为默认值，0 或无法解析时报错
+fn parse_max_file_size(value: Option<&str>) -> Result<u64, String> {
+    let Some(value) = value else {
+        return Ok(storage::DEFAULT_MAX_FILE_SIZE);
+    };
+    match value.parse::<u64>() {
+        Ok(bytes) if bytes > 0 => Ok(bytes),
+        _ => Err(format!(
+            "Invalid --max-file-size {:?}: expected a positive number of bytes",
+            value
+        )),
     }
 }
 
+/// 解析 `--rate-limit` / `--rate-limit-burst`；未设置速率时不限流
+///
+/// 速率须为正数，突发容量须不小于 1（默认等于速率向上取整）；无法解析或越界时报错
+fn parse_rate_limit(rate: Option<&str>, burst: Option<&str>) -> Result<Option<(f64, f64)>, String> {
+    let Some(rate_str) = rate else {
+        return match burst {
+            Some(_) => Err("--rate-limit-burst requires --rate-limit".to_string()),
+            None => Ok(None),
+        };
+    };
+    let rate = match rate_str.parse::<f64>() {
+        Ok(r) if r.is_finite() && r > 0.0 => r,
+        _ => {
+            return Err(format!(
+                "Invalid --rate-limit {:?}: expected a positive number of requests per second",
+                rate_str
+            ))
+        }
+    };
+    let burst = match burst {
+        None => rate.ceil(),
+        Some(b) => match b.parse::<f64>() {
+            Ok(v) if v.is_finite() && v >= 1.0 => v,
+            _ => {
+                return Err(format!(
+                    "Invalid --rate-limit-burst {:?}: expected a number no less than 1",
+                    b
+                ))
+            }
+        },
+    };
+    Ok(Some((rate, burst)))
+}
+
 fn parse_arg(args: &[String], flag: &str) -> Option<String> {
     args.iter()
         .position(|a| a == flag)
@@ -29,7 +195,21 @@ fn parse_arg(args: &[String], flag: &str) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-fn init(config_dir: &str) {
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|a| a == flag)
+}
+
+/// 逗号分隔列表，去除空白和空项
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn init(config_dir: &str, options: &storage::LoadOptions) {
     let base = std::path::Path::new(config_dir);
     std::fs::create_dir_all(base.join("shared")).unwrap();
     std::fs::create_dir_all(base.join("projects/example")).unwrap();
@@ -52,52 +232,74 @@ fn init(config_dir: &str) {
     )
     .unwrap();
 
+    // 严格模式下校验整个目录（可能包含已有文件）
+    if options.strict {
+        if let Err(e) = storage::Storage::load_with(base, options) {
+            eprintln!("Config validation failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     println!("Config directory initialized: {}", config_dir);
 }
 
-async fn serve(config_dir: &str, port: &str) {
+async fn serve(args: &ServeArgs) {
     use notify::{Event, EventKind, RecursiveMode, Watcher};
-    use std::sync::Arc;
-    use tokio::sync::RwLock;
 
-    let config_path = std::path::PathBuf::from(config_dir);
-    let center = match core::ConfigCenter::new(&config_path) {
-        Ok(c) => c,
+    let config_path = std::path::PathBuf::from(&args.config_dir);
+    let center = match core::ConfigCenter::with_options(&config_path, &args.options) {
+        Ok(c) => c
+            .with_admin_key(std::env::var("CONFIGAI_ADMIN_KEY").ok())
+            .with_secrets_dir(args.secrets_dir.as_ref().map(std::path::PathBuf::from)),
         Err(e) => {
             eprintln!("Failed to initialize: {}", e);
             std::process::exit(1);
         }
     };
 
-    let state: api::AppState = Arc::new(RwLock::new(center));
+    let mut state = api::AppState::new(center);
+    if let Some(path) = &args.audit_log {
+        match api::audit::AuditLog::default().with_file(std::path::Path::new(path)) {
+            Ok(audit) => state = state.with_audit_log(audit),
+            Err(e) => {
+                eprintln!("Failed to open audit log {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some((rate, burst)) = args.rate_limit {
+        state = state.with_rate_limiter(api::rate_limit::RateLimiter::new(rate, burst));
+        tracing::info!("Rate limit: {} req/s per key, burst {}", rate, burst);
+    }
     let reload_state = state.clone();
-    let reload_path = config_path.clone();
 
-    // File watcher - only react to yaml file changes
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
+    // File watcher - only react to config file changes
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<std::path::PathBuf>>(64);
 
-    let watch_path = config_path.clone();
+    // Watch the real directory: events under a symlinked path may be missed
+    let watch_path = config_path
+        .canonicalize()
+        .unwrap_or_else(|_| config_path.clone());
     std::thread::spawn(move || {
         let tx = tx;
-        let mut watcher =
-            notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
-                if let Ok(event) = res {
-                    if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
-                        return;
-                    }
-                    // Only trigger for yaml/yml files
-                    let is_yaml = event.paths.iter().any(|p| {
-                        p.extension()
-                            .and_then(|e| e.to_str())
-                            .map(|e| e == "yaml" || e == "yml")
-                            .unwrap_or(false)
-                    });
-                    if is_yaml {
-                        let _ = tx.blocking_send(());
-                    }
+        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+            if let Ok(event) = res {
+                if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                    return;
                 }
-            })
-            .expect("Failed to create file watcher");
+                // Only trigger for config files (yaml/yml/json/toml) or symlink swaps
+                // (Kubernetes ConfigMap mounts atomically replace a `..data` symlink)
+                let is_config = event.paths.iter().any(|p| {
+                    storage::has_config_extension(p)
+                        || p.symlink_metadata()
+                            .is_ok_and(|m| m.file_type().is_symlink())
+                });
+                if is_config {
+                    let _ = tx.blocking_send(event.paths);
+                }
+            }
+        })
+        .expect("Failed to create file watcher");
 
         // Only watch if config dir exists
         if watch_path.exists() {
@@ -113,27 +315,97 @@ async fn serve(config_dir: &str, port: &str) {
 
     // Background reload with debounce
     tokio::spawn(async move {
-        while rx.recv().await.is_some() {
-            // Debounce: wait 500ms and drain any additional notifications
+        while let Some(mut changed) = rx.recv().await {
+            // Debounce: wait 500ms and collect paths from any additional notifications
             tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            while rx.try_recv().is_ok() {}
+            while let Ok(paths) = rx.try_recv() {
+                changed.extend(paths);
+            }
 
-            match core::ConfigCenter::new(&reload_path) {
-                Ok(new_center) => {
-                    let mut center = reload_state.write().await;
-                    *center = new_center;
-                    tracing::info!("Config reloaded");
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to reload config: {}", e);
-                }
+            // Only rescan the projects whose files changed
+            match reload_state.reload(&changed) {
+                Ok(()) => tracing::info!("Config reloaded"),
+                Err(e) => tracing::warn!("Failed to reload config: {}", e),
             }
         }
     });
 
-    let router = api::create_router(state);
-    let addr = format!("0.0.0.0:{}", port);
+    let router = api::create_router_with(state, &args.router_options);
+    let addr = format!("0.0.0.0:{}", args.port);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     tracing::info!("API Server started: http://{}", addr);
     axum::serve(listener, router).await.unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_format() {
+        let args: Vec<String> = ["configai", "serve", "--log-format", "json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let value = parse_arg(&args, "--log-format");
+        assert_eq!(parse_log_format(value.as_deref()), LogFormat::Json);
+
+        assert_eq!(parse_log_format(None), LogFormat::Text);
+        assert_eq!(parse_log_format(Some("text")), LogFormat::Text);
+        assert_eq!(parse_log_format(Some("JSON")), LogFormat::Json);
+        assert_eq!(parse_log_format(Some("xml")), LogFormat::Text);
+    }
+
+    #[test]
+    fn test_parse_request_timeout() {
+        use std::time::Duration;
+
+        assert_eq!(
+            parse_request_timeout(None),
+            Ok(api::DEFAULT_REQUEST_TIMEOUT)
+        );
+        assert_eq!(parse_request_timeout(Some("5")), Ok(Duration::from_secs(5)));
+        for bad in ["0", "-1", "1.5", "30s", ""] {
+            assert!(parse_request_timeout(Some(bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_max_file_size() {
+        assert_eq!(
+            parse_max_file_size(None),
+            Ok(storage::DEFAULT_MAX_FILE_SIZE)
+        );
+        assert_eq!(parse_max_file_size(Some("1048576")), Ok(1048576));
+        for bad in ["0", "-1", "10MB", "1.5", ""] {
+            assert!(parse_max_file_size(Some(bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(parse_rate_limit(None, None), Ok(None));
+        assert_eq!(parse_rate_limit(Some("2.5"), None), Ok(Some((2.5, 3.0))));
+        assert_eq!(
+            parse_rate_limit(Some("10"), Some("20")),
+            Ok(Some((10.0, 20.0)))
+        );
+        for bad in ["0", "-1", "NaN", "inf", "10/s", ""] {
+            assert!(parse_rate_limit(Some(bad), None).is_err(), "{}", bad);
+        }
+        for bad in ["0.5", "0", "-1", "NaN", "x"] {
+            assert!(parse_rate_limit(Some("10"), Some(bad)).is_err(), "{}", bad);
+        }
+        assert!(parse_rate_limit(None, Some("20")).is_err());
+    }
+
+    #[test]
+    fn test_parse_log_level() {
+        assert_eq!(parse_log_level(None), LevelFilter::INFO);
+        assert_eq!(parse_log_level(Some("trace")), LevelFilter::TRACE);
+        assert_eq!(parse_log_level(Some("debug")), LevelFilter::DEBUG);
+        assert_eq!(parse_log_level(Some("WARN")), LevelFilter::WARN);
+        assert_eq!(parse_log_level(Some("error")), LevelFilter::ERROR);
+        assert_eq!(parse_log_level(Some("verbose")), LevelFilter::INFO);
+    }
+}
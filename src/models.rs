@@ -9,12 +9,46 @@ pub struct ProjectMeta {
     pub description: Option<String>,
     #[serde(default)]
     pub api_keys: Vec<ApiKeyEntry>,
+    /// 分组标签，如 `team:payments`、`tier:prod`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 引用的共享组（`shared/<组名>/`），按顺序叠加在全局共享配置之上，后者覆盖前者
+    #[serde(default)]
+    pub shared_groups: Vec<String>,
+    /// 导出环境变量时的 key 顺序（配置 key 名），未列出的 key 按名称排在其后
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// 已归档：数据保留，但不出现在默认项目列表中，也不能通过 API 读取配置
+    #[serde(default)]
+    pub archived: bool,
 }
 
 /// API Key 条目
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ApiKeyEntry {
     pub key: String,
+    /// 可读标签，用于日志和审计
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl ApiKeyEntry {
+    /// 日志/审计中使用的标识：优先 label，否则为脱敏后的 key
+    pub fn id(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => mask_key(&self.key),
+        }
+    }
+}
+
+/// 只保留前 4 个字符，其余以 **** 代替
+pub fn mask_key(key: &str) -> String {
+    if key.chars().count() < 8 {
+        return "****".to_string();
+    }
+    let visible: String = key.chars().take(4).collect();
+    format!("{}****", visible)
 }
 
 /// 完整的内存状态（从目录扫描构建）
@@ -24,6 +58,8 @@ pub struct ConfigState {
     pub projects: HashMap<String, ProjectData>,
     /// 共享配置：环境名 -> 配置 KV
     pub shared: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// 命名共享组：组名 -> 环境名 -> 配置 KV
+    pub shared_groups: HashMap<String, HashMap<String, HashMap<String, serde_json::Value>>>,
 }
 
 /// 单个项目的数据
@@ -1,32 +1,192 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use crate::error::Result;
+use crate::error::{ConfigError, Result};
 use crate::models::{ConfigState, ProjectData, ProjectMeta};
 
-/// 目录扫描式存储引擎
+/// 默认单个配置文件的大小上限：5 MiB
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// 加载选项
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// 严格模式：遇到无法解析的文件直接返回错误，而不是跳过
+    pub strict: bool,
+    /// 单个文件的大小上限（字节），超出的文件在读取前跳过（严格模式报错）
+    pub max_file_size: u64,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+}
+
+/// 配置层对应的来源文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    /// 加载时的修改时间；文件系统不支持时为 None
+    pub modified: Option<SystemTime>,
+}
+
+/// 目录扫描式存储引擎（也可直接持有内存中的状态）
 pub struct Storage {
     state: ConfigState,
-    config_dir: PathBuf,
+    /// 配置目录；纯内存存储为 None
+    config_dir: Option<PathBuf>,
+    options: LoadOptions,
+    warnings: Vec<String>,
+    /// 层标识（`shared/prod`、`shared/<组>/prod`、`projects/<项目>/prod`）-> 来源文件
+    sources: HashMap<String, SourceFile>,
 }
 
 impl Storage {
-    /// 从配置目录加载所有 YAML 文件
+    /// 从配置目录加载所有 YAML 文件（宽松模式，跳过无效文件）
     pub fn load(config_dir: &Path) -> Result<Self> {
+        Self::load_with(config_dir, &LoadOptions::default())
+    }
+
+    /// 严格模式加载：任一文件无法解析即返回错误
+    pub fn load_strict(config_dir: &Path) -> Result<Self> {
+        Self::load_with(
+            config_dir,
+            &LoadOptions {
+                strict: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn load_with(config_dir: &Path, options: &LoadOptions) -> Result<Self> {
+        let mut loader = Loader::new(options);
         let state = if config_dir.exists() {
-            let projects = load_projects(&config_dir.join("projects"));
-            let shared = load_shared(&config_dir.join("shared"));
-            ConfigState { projects, shared }
+            let projects = loader.load_projects(&config_dir.join("projects"))?;
+            let groups = referenced_groups(&projects);
+            let shared_dir = config_dir.join("shared");
+            let shared = loader.load_shared(&shared_dir, "shared", &groups)?;
+            let shared_groups = loader.load_shared_groups(&shared_dir, &groups)?;
+            for (name, project) in &projects {
+                for group in &project.meta.shared_groups {
+                    if !shared_groups.contains_key(group) {
+                        loader.warn(format!("项目 {} 引用了不存在的共享组: {}", name, group))?;
+                    }
+                }
+            }
+            ConfigState {
+                projects,
+                shared,
+                shared_groups,
+            }
         } else {
             ConfigState {
                 projects: HashMap::new(),
                 shared: HashMap::new(),
+                shared_groups: HashMap::new(),
             }
         };
 
         Ok(Self {
             state,
-            config_dir: config_dir.to_path_buf(),
+            config_dir: Some(config_dir.to_path_buf()),
+            options: options.clone(),
+            warnings: loader.warnings,
+            sources: loader.sources,
+        })
+    }
+
+    /// 纯内存存储：不读写磁盘，适用于测试和临时演示
+    pub fn from_state(state: ConfigState) -> Self {
+        Self {
+            state,
+            config_dir: None,
+            options: LoadOptions::default(),
+            warnings: Vec::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// 按相同来源重新加载：目录存储重新扫描，内存存储返回相同状态
+    pub fn reload(&self) -> Result<Self> {
+        match &self.config_dir {
+            Some(dir) => Self::load_with(dir, &self.options),
+            None => Ok(Self::from_state(self.state.clone())),
+        }
+    }
+
+    /// 增量重载：只重新扫描 `changed` 所在的项目目录，其余项目沿用内存中的状态。
+    /// `changed` 为空（变更未知）、涉及 shared/ 等项目目录之外的路径，
+    /// 或上次加载有警告（无法按项目区分）时退回整体重载
+    pub fn reload_paths(&self, changed: &[PathBuf]) -> Result<Self> {
+        let Some(dir) = self.config_dir.as_ref().filter(|_| !changed.is_empty()) else {
+            return self.reload();
+        };
+        // 监听器给出的路径与配置目录可能一个是相对路径、一个是绝对路径，
+        // 配置目录是符号链接时监听器给出的是链接目标下的路径
+        let projects_dir = std::path::absolute(dir.join("projects"))?;
+        let real_projects_dir = projects_dir.canonicalize().ok();
+        let mut names = Vec::new();
+        for path in changed {
+            let path = std::path::absolute(path)?;
+            let name = std::iter::once(&projects_dir)
+                .chain(real_projects_dir.as_ref())
+                .find_map(|root| path.strip_prefix(root).ok())
+                .and_then(|rest| rest.components().next())
+                .and_then(|c| c.as_os_str().to_str());
+            match name {
+                Some(name) => names.push(name.to_string()),
+                None => return self.reload(),
+            }
+        }
+        // 上次加载的警告无法按项目区分；新增项目可能与已有项目仅大小写不同
+        let collides = |name: &String| {
+            !self.state.projects.contains_key(name)
+                && self
+                    .state
+                    .projects
+                    .keys()
+                    .any(|existing| existing.to_lowercase() == name.to_lowercase())
+        };
+        if !self.warnings.is_empty() || names.iter().any(collides) {
+            return self.reload();
+        }
+
+        let mut loader = Loader::new(&self.options);
+        let mut state = self.state.clone();
+        names.sort();
+        names.dedup();
+        loader.sources = self.sources.clone();
+        for name in names {
+            let scope = format!("projects/{}/", name);
+            loader.sources.retain(|layer, _| !layer.starts_with(&scope));
+            let project_dir = projects_dir.join(&name);
+            if !project_dir.is_dir() {
+                state.projects.remove(&name);
+                continue;
+            }
+            let project = loader.load_project(&project_dir)?;
+            for group in &project.meta.shared_groups {
+                if !state.shared_groups.contains_key(group) {
+                    loader.warn(format!("项目 {} 引用了不存在的共享组: {}", name, group))?;
+                }
+            }
+            state.projects.insert(name, project);
+        }
+        // 引用的共享组变化会改变 shared/ 子目录的含义（共享组或嵌套环境）
+        if referenced_groups(&state.projects) != referenced_groups(&self.state.projects) {
+            return self.reload();
+        }
+
+        Ok(Self {
+            state,
+            config_dir: self.config_dir.clone(),
+            options: self.options.clone(),
+            warnings: loader.warnings,
+            sources: loader.sources,
         })
     }
 
@@ -34,135 +194,331 @@ impl Storage {
         &self.state
     }
 
-    pub fn config_dir(&self) -> &Path {
-        &self.config_dir
+    pub fn config_dir(&self) -> Option<&Path> {
+        self.config_dir.as_deref()
     }
-}
 
-/// 扫描 projects/ 目录，每个子目录是一个项目
-fn load_projects(projects_dir: &Path) -> HashMap<String, ProjectData> {
-    let mut projects = HashMap::new();
-    let entries = match std::fs::read_dir(projects_dir) {
-        Ok(e) => e,
-        Err(_) => return projects,
-    };
+    pub fn options(&self) -> &LoadOptions {
+        &self.options
+    }
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
-        }
-        let project_name = match path.file_name().and_then(|n| n.to_str()) {
-            Some(n) => n.to_string(),
-            None => continue,
-        };
+    /// 宽松模式下被跳过的文件及原因
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
 
-        let meta = load_project_meta(&path.join("project.yaml"));
-        let environments = load_env_configs(&path);
-        projects.insert(project_name, ProjectData { meta, environments });
+    /// 配置层的来源文件，如 `source("projects/app/prod")`；纯内存存储始终为 None
+    pub fn source(&self, layer: &str) -> Option<&SourceFile> {
+        self.sources.get(layer)
     }
+}
 
-    projects
+/// 单次加载过程的上下文：持有选项并收集警告
+struct Loader<'a> {
+    options: &'a LoadOptions,
+    warnings: Vec<String>,
+    sources: HashMap<String, SourceFile>,
 }
 
-/// 加载 project.yaml → ProjectMeta
-fn load_project_meta(path: &Path) -> ProjectMeta {
-    let content = match std::fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(_) => return ProjectMeta::default(),
-    };
-    match serde_yaml::from_str::<ProjectMeta>(&content) {
-        Ok(meta) => meta,
-        Err(e) => {
-            tracing::warn!("解析 project.yaml 失败 {:?}: {}", path, e);
-            ProjectMeta::default()
+impl<'a> Loader<'a> {
+    fn new(options: &'a LoadOptions) -> Self {
+        Self {
+            options,
+            warnings: Vec::new(),
+            sources: HashMap::new(),
         }
     }
-}
 
-/// 扫描项目目录下的 *.yaml（排除 project.yaml），每个文件是一个环境
-fn load_env_configs(project_dir: &Path) -> HashMap<String, HashMap<String, serde_json::Value>> {
-    let mut envs = HashMap::new();
-    let entries = match std::fs::read_dir(project_dir) {
-        Ok(e) => e,
-        Err(_) => return envs,
-    };
+    /// 记录配置层的来源文件及其修改时间
+    fn record_source(&mut self, layer: String, path: &Path) {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        self.sources.insert(
+            layer,
+            SourceFile {
+                path: path.to_path_buf(),
+                modified,
+            },
+        );
+    }
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !is_yaml_file(&path) {
-            continue;
+    /// 严格模式返回错误；宽松模式记录警告后继续
+    fn warn(&mut self, message: String) -> Result<()> {
+        if self.options.strict {
+            return Err(ConfigError::StorageError(message));
         }
-        let file_name = match path.file_stem().and_then(|n| n.to_str()) {
-            Some(n) => n.to_string(),
-            None => continue,
+        tracing::warn!("{}", message);
+        self.warnings.push(message);
+        Ok(())
+    }
+
+    /// 扫描 projects/ 目录，每个子目录是一个项目
+    fn load_projects(&mut self, projects_dir: &Path) -> Result<HashMap<String, ProjectData>> {
+        let mut projects = HashMap::new();
+        let entries = match std::fs::read_dir(projects_dir) {
+            Ok(e) => e,
+            Err(_) => return Ok(projects),
         };
-        // 跳过 project.yaml
-        if file_name == "project" {
-            continue;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let project_name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(n) => n.to_string(),
+                None => continue,
+            };
+
+            projects.insert(project_name, self.load_project(&path)?);
         }
-        if let Some(map) = load_yaml_map(&path) {
-            envs.insert(file_name, map);
+
+        // 大小写不敏感的文件系统（macOS、Windows）上这些目录会互相覆盖
+        let mut names: Vec<&String> = projects.keys().collect();
+        names.sort();
+        let mut seen: HashMap<String, &String> = HashMap::new();
+        for name in names {
+            if let Some(first) = seen.insert(name.to_lowercase(), name) {
+                self.warn(format!(
+                    "项目目录名仅大小写不同: {:?} 与 {:?}",
+                    projects_dir.join(first),
+                    projects_dir.join(name)
+                ))?;
+            }
         }
+
+        Ok(projects)
     }
 
-    envs
-}
+    /// 加载单个项目目录：project.yaml + 各环境配置
+    fn load_project(&mut self, project_dir: &Path) -> Result<ProjectData> {
+        let meta = self.load_project_meta(&project_dir.join("project.yaml"))?;
+        let name = project_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let environments = self.load_env_configs(project_dir, &format!("projects/{}", name))?;
+        Ok(ProjectData { meta, environments })
+    }
 
-/// 扫描 shared/ 目录，每个 *.yaml 是一个环境的共享配置
-fn load_shared(shared_dir: &Path) -> HashMap<String, HashMap<String, serde_json::Value>> {
-    let mut shared = HashMap::new();
-    let entries = match std::fs::read_dir(shared_dir) {
-        Ok(e) => e,
-        Err(_) => return shared,
-    };
+    /// 文件超出大小上限时记录警告并返回 false（严格模式返回错误），不读取内容
+    fn check_size(&mut self, path: &Path) -> Result<bool> {
+        let size = match std::fs::metadata(path) {
+            Ok(m) => m.len(),
+            // 交给后续读取报告错误
+            Err(_) => return Ok(true),
+        };
+        if size > self.options.max_file_size {
+            self.warn(format!(
+                "文件过大 {:?}: {} 字节，上限 {} 字节",
+                path, size, self.options.max_file_size
+            ))?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !is_yaml_file(&path) {
-            continue;
+    /// 加载 project.yaml → ProjectMeta
+    fn load_project_meta(&mut self, path: &Path) -> Result<ProjectMeta> {
+        if path.exists() && !self.check_size(path)? {
+            return Ok(ProjectMeta::default());
         }
-        let env_name = match path.file_stem().and_then(|n| n.to_str()) {
-            Some(n) => n.to_string(),
-            None => continue,
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return Ok(ProjectMeta::default()),
         };
-        if let Some(map) = load_yaml_map(&path) {
-            shared.insert(env_name, map);
+        match serde_yaml::from_str::<ProjectMeta>(&content) {
+            Ok(meta) => Ok(meta),
+            Err(e) => {
+                self.warn(format!("解析 project.yaml 失败 {:?}: {}", path, e))?;
+                Ok(ProjectMeta::default())
+            }
         }
     }
 
-    shared
-}
+    /// 扫描项目目录下的 *.yaml（排除 project.yaml），每个文件是一个环境；
+    /// `scope` 为来源记录中的层标识前缀
+    fn load_env_configs(
+        &mut self,
+        project_dir: &Path,
+        scope: &str,
+    ) -> Result<HashMap<String, HashMap<String, serde_json::Value>>> {
+        // 跳过 project.yaml
+        let skip = |path: &Path| path.is_file() && path.file_stem() == Some("project".as_ref());
+        let mut envs = HashMap::new();
+        let mut visited = HashSet::new();
+        self.scan_env_dir(project_dir, scope, "", &skip, &mut envs, &mut visited)?;
+        Ok(envs)
+    }
 
-/// 加载 YAML 文件为 HashMap<String, serde_json::Value>
-fn load_yaml_map(path: &Path) -> Option<HashMap<String, serde_json::Value>> {
-    let content = match std::fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::warn!("读取文件失败 {:?}: {}", path, e);
-            return None;
+    /// 递归扫描环境目录：子目录名作为环境名前缀（`regions/us-east.yaml` → `regions/us-east`），
+    /// 根下的 `environments/` 仅作容器，不计入环境名；`skip` 排除根下的特殊条目。
+    /// 子目录可以是符号链接；`visited` 记录已扫描目录的真实路径，指回上级的链接只扫描一次
+    fn scan_env_dir(
+        &mut self,
+        dir: &Path,
+        scope: &str,
+        prefix: &str,
+        skip: &dyn Fn(&Path) -> bool,
+        envs: &mut HashMap<String, HashMap<String, serde_json::Value>>,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        if let Ok(real) = dir.canonicalize() {
+            if !visited.insert(real) {
+                return self.warn(format!("跳过重复的目录（符号链接循环？）{:?}", dir));
+            }
         }
-    };
-    // serde_yaml -> serde_yaml::Value -> serde_json::Value 转换
-    let yaml_value: serde_yaml::Value = match serde_yaml::from_str(&content) {
-        Ok(v) => v,
-        Err(e) => {
-            tracing::warn!("解析 YAML 失败 {:?}: {}", path, e);
-            return None;
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(_) => return Ok(()),
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if prefix.is_empty() && skip(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                let dir_name = match path.file_name().and_then(|n| n.to_str()) {
+                    Some(n) if !n.starts_with('.') => n,
+                    _ => continue,
+                };
+                let nested = if prefix.is_empty() && dir_name == "environments" {
+                    String::new()
+                } else {
+                    format!("{}{}/", prefix, dir_name)
+                };
+                self.scan_env_dir(&path, scope, &nested, skip, envs, visited)?;
+                continue;
+            }
+            if !is_config_file(&path) {
+                continue;
+            }
+            let file_name = match path.file_stem().and_then(|n| n.to_str()) {
+                Some(n) => n,
+                None => continue,
+            };
+            let env_name = format!("{}{}", prefix, file_name);
+            if envs.contains_key(&env_name) {
+                self.warn(format!("环境名重复 {:?}: {}", path, env_name))?;
+                continue;
+            }
+            if let Some(map) = self.load_config_map(&path)? {
+                self.record_source(format!("{}/{}", scope, env_name), &path);
+                envs.insert(env_name, map);
+            }
         }
-    };
-    let json_value = yaml_to_json(yaml_value);
-    match json_value {
-        serde_json::Value::Object(map) => {
-            Some(map.into_iter().collect())
+
+        Ok(())
+    }
+
+    /// 扫描 shared/ 目录，每个配置文件是一个环境的共享配置，子目录与项目目录一样作为
+    /// 嵌套环境名前缀；被项目引用为共享组（`groups`）的子目录除外
+    fn load_shared(
+        &mut self,
+        shared_dir: &Path,
+        scope: &str,
+        groups: &HashSet<String>,
+    ) -> Result<HashMap<String, HashMap<String, serde_json::Value>>> {
+        let skip = |path: &Path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| groups.contains(n))
+        };
+        let mut shared = HashMap::new();
+        let mut visited = HashSet::new();
+        self.scan_env_dir(shared_dir, scope, "", &skip, &mut shared, &mut visited)?;
+        Ok(shared)
+    }
+
+    /// 加载被项目引用的共享组 `shared/<组名>/`，结构同 shared/；目录不存在的组不加载
+    #[allow(clippy::type_complexity)]
+    fn load_shared_groups(
+        &mut self,
+        shared_dir: &Path,
+        groups: &HashSet<String>,
+    ) -> Result<HashMap<String, HashMap<String, HashMap<String, serde_json::Value>>>> {
+        let mut loaded = HashMap::new();
+        for group in groups {
+            // 组名只能是 shared/ 下的一级目录名
+            if group.is_empty() || group.starts_with('.') || group.contains(['/', '\\']) {
+                continue;
+            }
+            let path = shared_dir.join(group);
+            if !path.is_dir() {
+                continue;
+            }
+            let envs = self.load_shared(&path, &format!("shared/{}", group), &HashSet::new())?;
+            loaded.insert(group.clone(), envs);
         }
-        _ => {
-            tracing::warn!("YAML 文件顶层不是 mapping {:?}", path);
-            None
+        Ok(loaded)
+    }
+
+    /// 加载配置文件为 HashMap<String, serde_json::Value>，按扩展名选择解析器
+    fn load_config_map(
+        &mut self,
+        path: &Path,
+    ) -> Result<Option<HashMap<String, serde_json::Value>>> {
+        if !self.check_size(path)? {
+            return Ok(None);
+        }
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                self.warn(format!("读取文件失败 {:?}: {}", path, e))?;
+                return Ok(None);
+            }
+        };
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let parsed = match extension {
+            "json" => serde_json::from_str(&content).map_err(|e| e.to_string()),
+            "toml" => parse_toml(&content),
+            _ => parse_yaml(&content),
+        };
+        let json_value = match parsed {
+            Ok(v) => v,
+            Err(e) => {
+                self.warn(format!("解析配置文件失败 {:?}: {}", path, e))?;
+                return Ok(None);
+            }
+        };
+        match json_value {
+            serde_json::Value::Object(map) => {
+                for key in map.keys() {
+                    if let Err(e) = validate_key(key) {
+                        if self.options.strict {
+                            return Err(e);
+                        }
+                        self.warn(format!("{:?}: {}", path, e))?;
+                    }
+                }
+                Ok(Some(map.into_iter().collect()))
+            }
+            _ => {
+                self.warn(format!("配置文件顶层不是 mapping {:?}", path))?;
+                Ok(None)
+            }
         }
     }
 }
 
+/// serde_yaml -> serde_yaml::Value -> serde_json::Value 转换
+fn parse_yaml(content: &str) -> std::result::Result<serde_json::Value, String> {
+    // 锚点/别名在解析时已展开，`<<` 合并键需显式应用
+    let mut yaml_value: serde_yaml::Value =
+        serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+    yaml_value.apply_merge().map_err(|e| e.to_string())?;
+    Ok(yaml_to_json(yaml_value))
+}
+
+/// toml -> toml::Value -> serde_json::Value 转换
+fn parse_toml(content: &str) -> std::result::Result<serde_json::Value, String> {
+    let toml_value: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+    Ok(toml_to_json(toml_value))
+}
+
 /// 递归将 serde_yaml::Value 转换为 serde_json::Value
 fn yaml_to_json(yaml: serde_yaml::Value) -> serde_json::Value {
     match yaml {
@@ -171,8 +527,11 @@ fn yaml_to_json(yaml: serde_yaml::Value) -> serde_json::Value {
         serde_yaml::Value::Number(n) => {
             if let Some(i) = n.as_i64() {
                 serde_json::Value::Number(i.into())
+            } else if let Some(u) = n.as_u64() {
+                // 超出 i64 范围的无符号整数（如 Snowflake ID）保持精确
+                serde_json::Value::Number(u.into())
             } else if let Some(f) = n.as_f64() {
-                serde_json::json!(f)
+                float_to_json(f)
             } else {
                 serde_json::Value::Null
             }
@@ -198,11 +557,85 @@ fn yaml_to_json(yaml: serde_yaml::Value) -> serde_json::Value {
     }
 }
 
-fn is_yaml_file(path: &Path) -> bool {
-    path.is_file()
-        && path
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| e == "yaml" || e == "yml")
-            .unwrap_or(false)
+/// JSON 无法表示 NaN/Infinity，按 JavaScript 的写法保留为字符串 "NaN"、"Infinity"、"-Infinity"
+fn float_to_json(f: f64) -> serde_json::Value {
+    if f.is_nan() {
+        serde_json::Value::String("NaN".to_string())
+    } else if f.is_infinite() {
+        let text = if f > 0.0 { "Infinity" } else { "-Infinity" };
+        serde_json::Value::String(text.to_string())
+    } else {
+        serde_json::json!(f)
+    }
+}
+
+/// 递归将 toml::Value 转换为 serde_json::Value，日期时间按原文保留为字符串
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::Number(i.into()),
+        toml::Value::Float(f) => float_to_json(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(arr) => {
+            serde_json::Value::Array(arr.into_iter().map(toml_to_json).collect())
+        }
+        toml::Value::Table(table) => serde_json::Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// 校验配置 key：只允许 `[A-Za-z0-9_.-]`，`${VAR}` 引用部分不参与校验。
+/// 宽松模式下不合法的 key 仍会加载并记录警告，严格模式下拒绝
+pub fn validate_key(key: &str) -> Result<()> {
+    let mut plain = String::new();
+    let mut rest = key;
+    while let Some(start) = rest.find("${") {
+        plain.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => {
+                plain.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    plain.push_str(rest);
+
+    let valid = !key.is_empty()
+        && plain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidKey(key.to_string()))
+    }
+}
+
+/// 可作为环境配置加载的文件扩展名
+pub const CONFIG_EXTENSIONS: &[&str] = &["yaml", "yml", "json", "toml"];
+
+/// 所有项目引用的共享组名：只有这些 shared/ 子目录按共享组加载
+fn referenced_groups(projects: &HashMap<String, ProjectData>) -> HashSet<String> {
+    projects
+        .values()
+        .flat_map(|p| p.meta.shared_groups.iter().cloned())
+        .collect()
+}
+
+/// 判断路径扩展名是否为支持的配置格式（不检查文件是否存在）
+pub fn has_config_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| CONFIG_EXTENSIONS.contains(&e))
+        .unwrap_or(false)
+}
+
+fn is_config_file(path: &Path) -> bool {
+    path.is_file() && has_config_extension(path)
 }
@@ -1,3 +1,5 @@
 mod dir;
 
-pub use dir::Storage;
+pub use dir::{
+    has_config_extension, validate_key, LoadOptions, SourceFile, Storage, DEFAULT_MAX_FILE_SIZE,
+};